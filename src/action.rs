@@ -10,13 +10,20 @@ use serde::{
 use strum::Display;
 use tokio::sync::RwLock;
 
-use crate::stats::data::{ResourceType, StatusType};
+use crate::stats::{
+  admin::{FrontendCommand, ServerState, WorkerResponse},
+  data::{ResourceType, StatusType},
+  collector::CollectorState,
+  master::WorkerSelection,
+};
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Serialize, Display, Deserialize)]
 pub enum TypingMode {
   #[default]
   Navigation,
   Filter,
+  /// A dialog has the keyboard, e.g. to confirm a command.
+  Dialog,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Serialize, Display, Deserialize)]
@@ -35,6 +42,13 @@ pub enum Action {
   Resume,
   Quit,
   Refresh,
+  SetRefreshInterval(f64),
+  ToggleFastRefresh,
+  NextChartWindow,
+  PrevChartWindow,
+  NextSort,
+  ReverseSort,
+  ToggleColumnPicker,
   Error(String),
   Help,
   MoveUp,
@@ -44,13 +58,43 @@ pub enum Action {
   Sticky,
   #[serde(with = "serde_arc_metrics")]
   MetricUpdate(Arc<HaproxyMetrics>),
+  EndpointMetricUpdate(String, #[serde(with = "serde_arc_metrics")] Arc<HaproxyMetrics>),
+  CollectorState(String, CollectorState),
+  NextEndpoint,
+  PrevEndpoint,
+  SelectEndpoint(String),
+  ActiveEndpoint(String),
+  SelectWorker(WorkerSelection),
   SelectResource(ResourceType),
   SelectStatus(StatusType),
   TypingMode(TypingMode),
   Filter(String),
   SwitchMode(Mode),
   UseItem(String),
+  /// Backend and name of a server to show details for.
+  UseServer(String, String),
+  UseFrontend(String),
   SelectItem,
+  /// Asks to set the state of the selected server.
+  SetServerState(ServerState),
+  /// Asks for a new weight for the selected server.
+  EditServerWeight,
+  /// Asks to set the selected server's weight back to its initial weight.
+  ResetServerWeight,
+  /// Asks for a new address and port for the selected server.
+  EditServerAddress,
+  /// Offers to enable, disable or force the health and agent checks of the selected server.
+  ServerChecks,
+  /// Asks to enable, disable or shut down the selected frontend.
+  SetFrontend(FrontendCommand),
+  /// Asks for a new maxconn for the selected frontend.
+  EditFrontendMaxconn,
+  /// The PID a stats socket reported in `show info`, or why it couldn't be asked.
+  HaproxyPid(String, Result<u32, String>),
+  /// Sends a CLI command to the active endpoint.
+  RunCommand(String),
+  /// What an endpoint's processes answered to a command, or why it couldn't be sent at all.
+  CommandResponse(String, String, Result<Vec<WorkerResponse>, String>),
 }
 
 
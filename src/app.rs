@@ -1,5 +1,7 @@
 use color_eyre::eyre::{eyre, Result};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use crossterm::event::KeyEvent;
 use ratatui::{
   layout::{Constraint, Direction, Layout},
@@ -10,49 +12,103 @@ use tokio::sync::mpsc;
 
 use crate::{
   action::{Action, TypingMode, MovementMode},
-  components::{fps::FpsCounter, items::Items, Component},
-  config::Config,
+  components::{
+    cluster::Cluster, fps::FpsCounter, frontend::FrontendDetails, items::Items, processes::Processes,
+    responses::Responses, server::ServerDetails, Component,
+  },
+  config::{Config, Endpoint},
   layouts::info::InfoLayout,
   layouts::home::HomeLayout,
+  layouts::stacked::StackedLayout,
   mode::Mode,
-  stats::{data::HaproxyStat, metrics::HaproxyMetrics, socket::Socket},
+  stats::{
+    collector::{CollectorCommand, CollectorState, StatsSource, DEFAULT_REFRESH_INTERVAL},
+    history::{History, DEFAULT_RETENTION},
+    data::HaproxyStat,
+    metrics::HaproxyMetrics,
+  },
   tui,
 };
 
 use tokio::task;
 
+/// Interval used by `Action::ToggleFastRefresh`, e.g. to follow a deploy as it happens.
+const FAST_REFRESH_INTERVAL: f64 = 1.0;
+
 pub struct App {
   pub config: Config,
   pub tick_rate: f64,
   pub frame_rate: f64,
   pub home: HomeLayout,
   pub graph: InfoLayout,
+  pub cluster: StackedLayout,
+  pub processes: StackedLayout,
+  pub server: StackedLayout,
+  pub frontend: StackedLayout,
+  pub responses: StackedLayout,
   pub should_quit: bool,
   pub should_suspend: bool,
   pub mode: Mode,
   pub last_tick_key_events: Vec<KeyEvent>,
-  pub haproxy_metrics: Arc<HaproxyMetrics>,
+  pub endpoints: Vec<Endpoint>,
+  pub active_endpoint: usize,
+  pub endpoint_metrics: HashMap<String, Arc<HaproxyMetrics>>,
+  pub endpoint_states: HashMap<String, CollectorState>,
+  pub collector_commands: HashMap<String, std::sync::mpsc::Sender<CollectorCommand>>,
+  /// Seconds between polls as configured, and as currently in effect.
+  pub refresh_interval: f64,
+  pub current_refresh_interval: f64,
   pub typing_mode: TypingMode,
 }
 
 impl App {
-  pub fn new(tick_rate: f64, frame_rate: f64) -> Result<Self> {
-    let config = Config::new()?;
+  pub fn new(tick_rate: f64, frame_rate: f64, socket: Option<String>, refresh_interval: Option<f64>) -> Result<Self> {
+    let mut config = Config::new()?;
+    let refresh_interval = refresh_interval.or(config.refresh_interval).unwrap_or(DEFAULT_REFRESH_INTERVAL);
+    if !refresh_interval.is_finite() || refresh_interval <= 0.0 {
+      return Err(eyre!("Refresh interval must be positive, got {}", refresh_interval));
+    }
+    let endpoints = match socket {
+      Some(socket) => vec![Endpoint { name: socket.clone(), address: socket }],
+      None if config.endpoints.is_empty() => {
+        vec![Endpoint { name: config.paths.socket.clone(), address: config.paths.socket.clone() }]
+      },
+      None => config.endpoints.clone(),
+    };
+    // paths.socket always points at the active endpoint, so components querying the socket follow it
+    config.paths.socket = endpoints[0].address.clone();
+    config.endpoints = endpoints.clone();
     let mode = Mode::Home;
     let home = HomeLayout::new();
     let graph = InfoLayout::new();
+    let cluster = StackedLayout::full_screen(Box::new(Cluster::new()));
+    let processes = StackedLayout::full_screen(Box::new(Processes::new()));
+    let server = StackedLayout::details(Box::new(ServerDetails::new()), 23);
+    let frontend = StackedLayout::details(Box::new(FrontendDetails::new()), 22);
+    let responses = StackedLayout::full_screen(Box::new(Responses::new()));
 
     Ok(Self {
       tick_rate,
       frame_rate,
       home,
       graph,
+      cluster,
+      processes,
+      server,
+      frontend,
+      responses,
       should_quit: false,
       should_suspend: false,
       config,
       mode,
       last_tick_key_events: Vec::new(),
-      haproxy_metrics: Arc::new(HaproxyMetrics::new()),
+      endpoints,
+      active_endpoint: 0,
+      endpoint_metrics: HashMap::new(),
+      endpoint_states: HashMap::new(),
+      collector_commands: HashMap::new(),
+      refresh_interval,
+      current_refresh_interval: refresh_interval,
       typing_mode: TypingMode::Navigation,
     })
   }
@@ -72,15 +128,27 @@ impl App {
     layout.register_config_handler(config)?;
     layout.init(Rect::new(0, 0, tui.size()?.width, tui.size()?.height))?;
 
-    let mut socket = Socket::new(self.config.paths.socket.to_string())?;
-    let socket_tx = action_tx.clone();
+    // Every endpoint gets its own collector task
+    for endpoint in &self.endpoints {
+      let source: StatsSource = endpoint.address.parse()?;
+      let mut collector = source.collector(self.config.stat_format);
+      let collector_tx = action_tx.clone();
+      let name = endpoint.name.clone();
+      let (command_tx, command_rx) = std::sync::mpsc::channel();
+      self.collector_commands.insert(name.clone(), command_tx);
+      let interval = Duration::from_secs_f64(self.current_refresh_interval);
+      let retention = self.config.history_retention.map(Duration::from_secs).unwrap_or(DEFAULT_RETENTION);
+
+      task::spawn_blocking(move || {
+        if let Err(e) = collector.collect(name.clone(), interval, History::new(retention), collector_tx, command_rx) {
+          log::error!("Collector for {} stopped: {:?}", name, e);
+        }
+      });
+    }
 
-    task::spawn_blocking(move || -> Result<()> {
-      socket.collect(socket_tx)?;
-      Ok(())
-    });
+    action_tx.send(Action::ActiveEndpoint(self.endpoints[self.active_endpoint].name.clone()))?;
 
-    'main: loop {
+    loop {
       use std::time::Instant;
       let now = Instant::now();
       if let Some(mut e) = tui.next().await {
@@ -109,7 +177,7 @@ impl App {
                 }
               };
             }
-            e = tui::Event::ModeKey(self.typing_mode.clone(), key);
+            e = tui::Event::ModeKey(self.typing_mode, key);
           },
           _ => {},
         }
@@ -125,8 +193,51 @@ impl App {
         }
 
         match action.clone() {
-          Action::MetricUpdate(metrics) => {
-            self.haproxy_metrics = metrics;
+          Action::EndpointMetricUpdate(endpoint, metrics) => {
+            if endpoint == self.endpoints[self.active_endpoint].name {
+              action_tx.send(Action::MetricUpdate(metrics.clone()))?;
+            }
+            self.endpoint_metrics.insert(endpoint, metrics);
+          },
+          Action::CollectorState(endpoint, state) => {
+            self.endpoint_states.insert(endpoint, state);
+          },
+          Action::NextEndpoint => {
+            self.switch_endpoint((self.active_endpoint + 1) % self.endpoints.len(), &action_tx)?;
+          },
+          Action::PrevEndpoint => {
+            let index = (self.active_endpoint + self.endpoints.len() - 1) % self.endpoints.len();
+            self.switch_endpoint(index, &action_tx)?;
+          },
+          Action::Refresh => self.send_collector_commands(CollectorCommand::Refresh),
+          Action::SetRefreshInterval(interval) => self.set_refresh_interval(interval),
+          Action::ToggleFastRefresh => {
+            if self.current_refresh_interval == FAST_REFRESH_INTERVAL {
+              self.set_refresh_interval(self.refresh_interval);
+            } else {
+              self.set_refresh_interval(FAST_REFRESH_INTERVAL);
+            }
+          },
+          Action::SelectWorker(worker) => {
+            let endpoint = &self.endpoints[self.active_endpoint].name;
+            if let Some(commands) = self.collector_commands.get(endpoint) {
+              commands.send(CollectorCommand::SelectWorker(worker))?;
+            }
+          },
+          Action::RunCommand(ref command) => {
+            let endpoint = self.endpoints[self.active_endpoint].name.clone();
+            let run = CollectorCommand::Run(command.clone());
+            let sent = self.collector_commands.get(&endpoint).map(|commands| commands.send(run));
+            if !matches!(sent, Some(Ok(()))) {
+              let error = format!("The collector for {} isn't running", endpoint);
+              action_tx.send(Action::CommandResponse(endpoint, command.clone(), Err(error)))?;
+            }
+          },
+          Action::SelectEndpoint(ref name) => {
+            match self.endpoints.iter().position(|endpoint| &endpoint.name == name) {
+              Some(index) => self.switch_endpoint(index, &action_tx)?,
+              None => log::warn!("Unknown endpoint: {}", name),
+            }
           },
           Action::MoveUp => {
             match self.typing_mode {
@@ -187,6 +298,21 @@ impl App {
             self.get_layout().register_action_handler(action_tx.clone())?;
             self.get_layout().register_config_handler(config)?;
             self.get_layout().init(Rect::new(0, 0, tui.size()?.width, tui.size()?.height))?;
+
+            // Layouts only see updates while they're active, so catch the new one up
+            let active = self.endpoints[self.active_endpoint].name.clone();
+            self.get_layout().update(Action::ActiveEndpoint(active))?;
+            for (endpoint, state) in self.endpoint_states.clone() {
+              self.get_layout().update(Action::CollectorState(endpoint, state))?;
+            }
+            if let Some(metrics) = self.endpoint_metrics.get(&self.endpoints[self.active_endpoint].name).cloned() {
+              self.get_layout().update(Action::MetricUpdate(metrics))?;
+            }
+            if mode == Mode::Cluster {
+              for (endpoint, metrics) in &self.endpoint_metrics {
+                self.cluster.update(Action::EndpointMetricUpdate(endpoint.clone(), metrics.clone()))?;
+              }
+            }
           },
           _ => {},
         }
@@ -212,10 +338,62 @@ impl App {
     Ok(())
   }
 
+  /// Sends a command to every endpoint's collector. A collector that has stopped is logged and skipped.
+  fn send_collector_commands(&self, command: CollectorCommand) {
+    for (endpoint, commands) in &self.collector_commands {
+      if commands.send(command.clone()).is_err() {
+        log::warn!("Collector for {} is not running, dropped {:?}", endpoint, command);
+      }
+    }
+  }
+
+  fn set_refresh_interval(&mut self, interval: f64) {
+    if !interval.is_finite() || interval <= 0.0 {
+      log::warn!("Ignoring invalid refresh interval {}", interval);
+      return;
+    }
+    log::info!("Refreshing every {}s", interval);
+    self.current_refresh_interval = interval;
+    self.send_collector_commands(CollectorCommand::SetInterval(Duration::from_secs_f64(interval)));
+  }
+
+  fn switch_endpoint(&mut self, index: usize, action_tx: &mpsc::UnboundedSender<Action>) -> Result<()> {
+    if index == self.active_endpoint {
+      return Ok(());
+    }
+
+    self.active_endpoint = index;
+    let endpoint = self.endpoints[index].clone();
+    log::info!("Switching to endpoint {} ({})", endpoint.name, endpoint.address);
+
+    self.config.paths.socket = endpoint.address;
+    let config = self.config.clone();
+    self.home.register_config_handler(config.clone())?;
+    self.graph.register_config_handler(config.clone())?;
+    self.cluster.register_config_handler(config.clone())?;
+    self.processes.register_config_handler(config.clone())?;
+    self.server.register_config_handler(config.clone())?;
+    self.frontend.register_config_handler(config.clone())?;
+    self.responses.register_config_handler(config)?;
+
+    let metrics = self.endpoint_metrics.get(&endpoint.name).cloned().unwrap_or_default();
+    let state = self.endpoint_states.get(&endpoint.name).cloned().unwrap_or_default();
+    action_tx.send(Action::ActiveEndpoint(endpoint.name.clone()))?;
+    action_tx.send(Action::CollectorState(endpoint.name, state))?;
+    action_tx.send(Action::MetricUpdate(metrics))?;
+
+    Ok(())
+  }
+
   fn get_layout(&mut self) -> &mut dyn Component {
     match self.mode {
       Mode::Home => &mut self.home,
-      Mode::Info => &mut self.graph
+      Mode::Info => &mut self.graph,
+      Mode::Cluster => &mut self.cluster,
+      Mode::Processes => &mut self.processes,
+      Mode::Server => &mut self.server,
+      Mode::Frontend => &mut self.frontend,
+      Mode::Responses => &mut self.responses,
     }
   }
 }
@@ -18,4 +18,20 @@ pub struct Cli {
     default_value_t = 60.0
   )]
   pub frame_rate: f64,
+
+  #[arg(
+    short,
+    long,
+    value_name = "ADDRESS",
+    help = "HAProxy stats source: unix:/path, tcp:host:port, a plain path or an http(s):// stats page URL. Overrides paths.socket"
+  )]
+  pub socket: Option<String>,
+
+  #[arg(
+    short,
+    long,
+    value_name = "SECONDS",
+    help = "Seconds between polls of each HAProxy instance. Overrides refresh_interval from the config"
+  )]
+  pub refresh_interval: Option<f64>,
 }
@@ -1,6 +1,9 @@
 use color_eyre::eyre::Result;
 use crossterm::event::{KeyEvent, MouseEvent};
-use ratatui::layout::Rect;
+use ratatui::{
+  layout::Rect,
+  style::{Style, Stylize},
+};
 use tokio::sync::mpsc::UnboundedSender;
 
 use crate::{
@@ -9,12 +12,24 @@ use crate::{
   tui::{Event, Frame},
 };
 
+pub mod charts;
+pub mod columns;
+pub mod details;
+pub mod dialog;
 pub mod fps;
+pub mod frontend;
 pub mod home;
 pub mod items;
+pub mod latency;
 pub mod menu;
 pub mod status;
+pub mod statusbar;
 pub mod config;
+pub mod cluster;
+pub mod processes;
+pub mod query;
+pub mod responses;
+pub mod server;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 /// Implementors of this trait can be registered with the main application loop and will be able to receive events,
@@ -144,3 +159,8 @@ pub trait Component {
     Ok(None)
   }
 }
+
+/// Dims everything already drawn in `area`, used to mark data from a collector that has lost its connection.
+pub fn grey_out(f: &mut Frame<'_>, area: Rect) {
+  f.buffer_mut().set_style(area, Style::new().dark_gray().not_bold());
+}
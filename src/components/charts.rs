@@ -0,0 +1,230 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{grey_out, Component, Frame};
+use crate::{
+  action::Action,
+  stats::{history::StatKey, metrics::HaproxyMetrics, rates::format_rate},
+};
+
+/// Time windows the charts can cover, switched between with `NextChartWindow`/`PrevChartWindow`.
+pub const CHART_WINDOWS: [Duration; 5] = [
+  Duration::from_secs(60),
+  Duration::from_secs(300),
+  Duration::from_secs(900),
+  Duration::from_secs(1800),
+  Duration::from_secs(3600),
+];
+
+const DEFAULT_WINDOW: usize = 1;
+
+/// Request rate, sessions, response codes and queue of the selected backend over a window of the retained history.
+pub struct Charts {
+  metrics: Option<Arc<HaproxyMetrics>>,
+  selected_backend: Option<String>,
+  window: usize,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+impl Default for Charts {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Short label for a window, e.g. `5m` or `1h`.
+fn format_window(window: Duration) -> String {
+  let seconds = window.as_secs();
+  if seconds >= 3600 && seconds.is_multiple_of(3600) {
+    format!("{}h", seconds / 3600)
+  } else if seconds >= 60 && seconds.is_multiple_of(60) {
+    format!("{}m", seconds / 60)
+  } else {
+    format!("{}s", seconds)
+  }
+}
+
+/// Turns a series into chart points, with time as seconds before `latest` so the x axis ends at 0.
+fn points(latest: DateTime<Local>, series: impl IntoIterator<Item = (DateTime<Local>, f64)>) -> Vec<(f64, f64)> {
+  series
+    .into_iter()
+    .map(|(time, value)| (-((latest - time).num_milliseconds() as f64 / 1000.0), value))
+    .collect()
+}
+
+fn line<'a>(name: &'a str, color: Color, data: &'a [(f64, f64)]) -> Dataset<'a> {
+  Dataset::default().name(name).graph_type(GraphType::Line).marker(symbols::Marker::Braille).fg(color).data(data)
+}
+
+fn max_value<'a>(series: impl IntoIterator<Item = &'a Vec<(f64, f64)>>) -> f64 {
+  series.into_iter().flatten().map(|(_, value)| *value).fold(0.0, f64::max)
+}
+
+impl Charts {
+  pub fn new() -> Self {
+    Self { metrics: None, selected_backend: None, window: DEFAULT_WINDOW, endpoint: None, stale: false }
+  }
+
+  fn window(&self) -> Duration {
+    CHART_WINDOWS[self.window]
+  }
+
+  fn backend_key(&self) -> Option<StatKey> {
+    let instant = self.metrics.as_ref()?.instant.as_ref()?;
+    instant.data.backends.iter().find(|backend| backend.name == self.selected_backend).map(|backend| backend.key())
+  }
+
+  fn block(&self, title: &str) -> Block<'static> {
+    Block::default().borders(Borders::ALL).title(format!(" {} (last {}) ", title, format_window(self.window())))
+  }
+
+  fn chart<'a>(&self, title: &str, datasets: Vec<Dataset<'a>>, max: f64, label: fn(f64) -> String) -> Chart<'a> {
+    let window = self.window().as_secs_f64();
+    let max = if max > 0.0 { max * 1.1 } else { 1.0 };
+    Chart::new(datasets)
+      .block(self.block(title))
+      .x_axis(Axis::default().bounds([-window, 0.0]).labels([
+        format!("-{}", format_window(self.window())),
+        format!("-{}", format_window(self.window() / 2)),
+        "now".to_string(),
+      ]))
+      .y_axis(Axis::default().bounds([0.0, max]).labels(["0".to_string(), label(max / 2.0), label(max)]))
+  }
+
+  fn draw_charts(&self, f: &mut Frame<'_>, area: Rect, metrics: &HaproxyMetrics, key: StatKey) {
+    let history = &metrics.history;
+    let latest = match history.snapshots().next_back() {
+      Some(snapshot) => snapshot.time,
+      None => return,
+    };
+    let window = self.window();
+    let rate = |field: &str| points(latest, history.rate_series(key, field, window));
+    let gauge = |field: &str| {
+      let series = history.series(key, window);
+      points(latest, series.into_iter().filter_map(|(time, sample)| Some((time, sample.get(field)?))))
+    };
+
+    let rows = Layout::default()
+      .direction(Direction::Vertical)
+      .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+      .split(area);
+    let top = Layout::default()
+      .direction(Direction::Horizontal)
+      .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+      .split(rows[0]);
+    let bottom = Layout::default()
+      .direction(Direction::Horizontal)
+      .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+      .split(rows[1]);
+
+    // TCP backends don't count requests, so fall back to sessions like the rate columns do
+    let mut requests = rate("req_tot");
+    if requests.is_empty() {
+      requests = rate("stot");
+    }
+    let dataset = line("requests", Color::Cyan, &requests);
+    f.render_widget(self.chart("Requests/s", vec![dataset], max_value([&requests]), |v| format_rate(Some(v))), top[0]);
+
+    let sessions = gauge("scur");
+    let limit = gauge("slim");
+    let mut datasets = vec![line("current", Color::Cyan, &sessions)];
+    if !limit.is_empty() {
+      datasets.push(line("limit", Color::Red, &limit));
+    }
+    f.render_widget(self.chart("Sessions", datasets, max_value([&sessions, &limit]), |v| format!("{:.0}", v)), top[1]);
+
+    let codes = [
+      ("2xx", rate("hrsp_2xx"), Color::Green),
+      ("4xx", rate("hrsp_4xx"), Color::Yellow),
+      ("5xx", rate("hrsp_5xx"), Color::Red),
+    ];
+    let datasets = codes.iter().map(|(name, data, color)| line(name, *color, data)).collect();
+    let max = max_value(codes.iter().map(|(_, data, _)| data));
+    f.render_widget(self.chart("HTTP responses/s", datasets, max, |v| format_rate(Some(v))), bottom[0]);
+
+    let queue: Vec<u64> = gauge("qcur").iter().map(|(_, value)| *value as u64).collect();
+    let current = queue.last().copied().unwrap_or_default();
+    let peak = queue.iter().copied().max().unwrap_or_default();
+    // Newest values on the right, like the charts
+    let width = bottom[1].width.saturating_sub(2) as usize;
+    let visible = &queue[queue.len().saturating_sub(width)..];
+    let sparkline = Sparkline::default()
+      .block(self.block(&format!("Queue: {} now, {} max", current, peak)))
+      .data(visible)
+      .max(peak.max(1))
+      .yellow();
+    f.render_widget(sparkline, bottom[1]);
+  }
+}
+
+impl Component for Charts {
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::MetricUpdate(metrics) => self.metrics = Some(metrics),
+      Action::UseItem(backend_name) => self.selected_backend = Some(backend_name),
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) if self.endpoint.as_ref() == Some(&endpoint) => {
+        self.stale = state.is_stale();
+      },
+      Action::NextChartWindow => self.window = (self.window + 1) % CHART_WINDOWS.len(),
+      Action::PrevChartWindow => self.window = (self.window + CHART_WINDOWS.len() - 1) % CHART_WINDOWS.len(),
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let (metrics, key) = match (self.metrics.clone(), self.backend_key()) {
+      (Some(metrics), Some(key)) => (metrics, key),
+      _ => {
+        let placeholder = Paragraph::new("No data for this backend yet").block(Block::default().borders(Borders::ALL));
+        f.render_widget(placeholder, area);
+        return Ok(());
+      },
+    };
+
+    self.draw_charts(f, area, &metrics, key);
+    if self.stale {
+      grey_out(f, area);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_format_window() {
+    assert_eq!(format_window(Duration::from_secs(30)), "30s");
+    assert_eq!(format_window(Duration::from_secs(150)), "150s");
+    assert_eq!(format_window(Duration::from_secs(300)), "5m");
+    assert_eq!(format_window(Duration::from_secs(3600)), "1h");
+  }
+
+  #[test]
+  fn test_points() {
+    let latest = Local::now();
+    let series = vec![(latest - chrono::Duration::seconds(90), 3.0), (latest, 5.0)];
+    assert_eq!(points(latest, series), vec![(-90.0, 3.0), (0.0, 5.0)]);
+  }
+
+  #[test]
+  fn test_cycle_window() {
+    let mut charts = Charts::new();
+    charts.update(Action::PrevChartWindow).unwrap();
+    assert_eq!(charts.window(), Duration::from_secs(60));
+    charts.update(Action::PrevChartWindow).unwrap();
+    assert_eq!(charts.window(), Duration::from_secs(3600));
+    charts.update(Action::NextChartWindow).unwrap();
+    assert_eq!(charts.window(), Duration::from_secs(60));
+  }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, Frame};
+use crate::{
+  action::{Action, MovementMode},
+  config::Config,
+  stats::{collector::CollectorState, metrics::HaproxyMetrics},
+};
+
+/// The state of a single backend or server on one node.
+#[derive(Debug, Clone, PartialEq)]
+struct ClusterCell {
+  status: String,
+  weight: Option<i64>,
+  check_status: Option<String>,
+}
+
+impl ClusterCell {
+  fn style(&self) -> Style {
+    let status = self.status.as_str();
+    if status.starts_with("UP") || status == "OPEN" {
+      Style::default().fg(Color::Green)
+    } else if status.starts_with("DOWN") || status == "NOLB" {
+      Style::default().fg(Color::Red)
+    } else {
+      Style::default().fg(Color::Yellow)
+    }
+  }
+}
+
+impl std::fmt::Display for ClusterCell {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.status)?;
+    if let Some(weight) = self.weight {
+      write!(f, " w{}", weight)?;
+    }
+    if let Some(check_status) = &self.check_status {
+      write!(f, " {}", check_status)?;
+    }
+    Ok(())
+  }
+}
+
+/// A backend (`server` is `None`) or server, with one cell per node.
+#[derive(Debug, Clone, PartialEq)]
+struct ClusterRow {
+  backend: String,
+  server: Option<String>,
+  cells: Vec<Option<ClusterCell>>,
+  diverged: bool,
+}
+
+/// Lines up every backend and server across nodes, in the order the first node reports them. Nodes without
+/// data yet are left out of the comparison so that a slow collector doesn't flag every row.
+fn build_rows(endpoints: &[String], metrics: &HashMap<String, Arc<HaproxyMetrics>>) -> Vec<ClusterRow> {
+  let mut rows: Vec<ClusterRow> = Vec::new();
+  let mut index: HashMap<(String, Option<String>), usize> = HashMap::new();
+  let mut reporting = vec![false; endpoints.len()];
+
+  for (column, endpoint) in endpoints.iter().enumerate() {
+    let instant = match metrics.get(endpoint).and_then(|metrics| metrics.instant.as_ref()) {
+      Some(instant) => instant,
+      None => continue,
+    };
+    reporting[column] = true;
+
+    for stat in &instant.data.raw {
+      let server = match stat.resource_type {
+        Some(1) => None,
+        Some(2) => stat.svname.clone(),
+        _ => continue,
+      };
+      let key = (stat.pxname.clone().unwrap_or_default(), server);
+
+      let row = *index.entry(key.clone()).or_insert_with(|| {
+        rows.push(ClusterRow { backend: key.0, server: key.1, cells: vec![None; endpoints.len()], diverged: false });
+        rows.len() - 1
+      });
+
+      rows[row].cells[column] = Some(ClusterCell {
+        status: stat.status.clone().unwrap_or_default(),
+        weight: stat.weight,
+        check_status: stat.check_status.clone().filter(|check_status| !check_status.is_empty()),
+      });
+    }
+  }
+
+  // show stat lists servers before their BACKEND line, group them under the backend instead
+  let mut backend_order: HashMap<String, usize> = HashMap::new();
+  for row in &rows {
+    let next = backend_order.len();
+    backend_order.entry(row.backend.clone()).or_insert(next);
+  }
+  rows.sort_by_key(|row| (backend_order[&row.backend], row.server.is_some()));
+
+  for row in &mut rows {
+    let mut cells = row.cells.iter().zip(&reporting).filter(|(_, reporting)| **reporting).map(|(cell, _)| cell);
+    if let Some(first) = cells.next() {
+      row.diverged = cells.any(|cell| cell != first);
+    }
+  }
+
+  rows
+}
+
+#[derive(Default)]
+pub struct Cluster {
+  config: Config,
+  state: TableState,
+  metrics: HashMap<String, Arc<HaproxyMetrics>>,
+  states: HashMap<String, CollectorState>,
+  rows: Vec<ClusterRow>,
+}
+
+impl Cluster {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn endpoints(&self) -> Vec<String> {
+    self.config.endpoints.iter().map(|endpoint| endpoint.name.clone()).collect()
+  }
+
+  fn update_rows(&mut self) {
+    self.rows = build_rows(&self.endpoints(), &self.metrics);
+    if self.state.selected().is_none_or(|selected| selected >= self.rows.len()) {
+      self.state.select(if self.rows.is_empty() { None } else { Some(0) });
+    }
+  }
+}
+
+impl Component for Cluster {
+  fn register_config_handler(&mut self, config: Config) -> Result<()> {
+    self.config = config;
+    self.update_rows();
+    Ok(())
+  }
+
+  fn move_down(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    if !self.rows.is_empty() {
+      let selected = self.state.selected().map_or(0, |selected| (selected + 1) % self.rows.len());
+      self.state.select(Some(selected));
+    }
+    Ok(None)
+  }
+
+  fn move_up(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    if !self.rows.is_empty() {
+      let selected = self.state.selected().map_or(0, |selected| (selected + self.rows.len() - 1) % self.rows.len());
+      self.state.select(Some(selected));
+    }
+    Ok(None)
+  }
+
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::EndpointMetricUpdate(endpoint, metrics) => {
+        self.metrics.insert(endpoint, metrics);
+        self.update_rows();
+      },
+      Action::CollectorState(endpoint, state) => {
+        self.states.insert(endpoint, state);
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let endpoints = self.endpoints();
+    let diverged = self.rows.iter().filter(|row| row.diverged).count();
+    // Nodes whose collector lost its connection keep their last known state, greyed out
+    let stale: Vec<bool> =
+      endpoints.iter().map(|endpoint| self.states.get(endpoint).is_some_and(CollectorState::is_stale)).collect();
+
+    let mut headers = vec![Cell::from("")];
+    headers.extend(endpoints.iter().zip(&stale).map(|(endpoint, stale)| match stale {
+      true => Cell::from(format!("{} (stale)", endpoint)).style(Style::default().fg(Color::DarkGray)),
+      false => Cell::from(endpoint.clone()),
+    }));
+
+    let rows = self.rows.iter().map(|row| {
+      let name = match &row.server {
+        Some(server) => Span::raw(format!("└ {}", server)),
+        None => Span::raw(row.backend.clone()).bold(),
+      };
+      let name = if row.diverged { name.red().bold() } else { name };
+
+      let mut cells = vec![Cell::from(name)];
+      cells.extend(row.cells.iter().zip(&stale).map(|(cell, stale)| match (cell, stale) {
+        (Some(cell), false) => Cell::from(cell.to_string()).style(cell.style()),
+        (Some(cell), true) => Cell::from(cell.to_string()).style(Style::default().fg(Color::DarkGray)),
+        (None, _) => Cell::from("-").style(Style::default().fg(Color::DarkGray)),
+      }));
+
+      let row_widget = Row::new(cells);
+      if row.diverged {
+        row_widget.style(Style::default().bg(Color::Indexed(52)))
+      } else {
+        row_widget
+      }
+    });
+
+    let mut widths = vec![Constraint::Min(30)];
+    widths.extend(endpoints.iter().map(|_| Constraint::Length(20)));
+
+    let table = Table::new(rows, widths)
+      .header(Row::new(headers).bold())
+      .row_highlight_style(Style::new().light_yellow())
+      .block(
+        Block::new()
+          .title(format!("Cluster ({} diverged)", diverged))
+          .borders(Borders::ALL)
+          .border_style(Style::default().fg(Color::White)),
+      );
+
+    f.render_stateful_widget(table, area, &mut self.state);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::stats::data::HaproxyStat;
+
+  fn metrics(server_status: &str, weight: i64) -> Arc<HaproxyMetrics> {
+    let mut stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let app1 = stats.iter_mut().find(|stat| stat.svname.as_deref() == Some("app1")).unwrap();
+    app1.status = Some(server_status.to_string());
+    app1.weight = Some(weight);
+
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(stats).unwrap();
+    Arc::new(metrics)
+  }
+
+  #[test]
+  fn test_build_rows() {
+    let endpoints = vec!["edge-1".to_string(), "edge-2".to_string(), "edge-3".to_string()];
+    let mut all = HashMap::new();
+    all.insert("edge-1".to_string(), metrics("UP", 1));
+    all.insert("edge-2".to_string(), metrics("UP", 1));
+
+    let rows = build_rows(&endpoints, &all);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].server, None);
+    assert_eq!(rows[1].server, Some("app1".to_string()));
+    assert!(rows.iter().all(|row| !row.diverged));
+    // edge-3 hasn't reported yet
+    assert!(rows.iter().all(|row| row.cells[2].is_none()));
+
+    all.insert("edge-3".to_string(), metrics("UP", 5));
+    let rows = build_rows(&endpoints, &all);
+    assert!(!rows[0].diverged);
+    assert!(rows[1].diverged);
+    assert!(!rows[2].diverged);
+
+    all.insert("edge-3".to_string(), metrics("DOWN", 1));
+    let rows = build_rows(&endpoints, &all);
+    assert!(rows[1].diverged);
+    assert_eq!(rows[1].cells[2].as_ref().unwrap().status, "DOWN");
+  }
+}
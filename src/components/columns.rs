@@ -0,0 +1,289 @@
+use std::collections::BTreeSet;
+
+use ratatui::widgets::Cell;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use super::{
+  details::{millis, seconds},
+  latency::{averages, latency_bar, maxima},
+};
+use crate::stats::{
+  data::HaproxyStat,
+  latency::Latency,
+  rates::{format_bytes, format_bytes_rate, format_percent, format_rate, Rates},
+};
+
+/// Width of columns that don't set their own.
+pub const COLUMN_WIDTH: u16 = 11;
+
+/// Latency columns need more room than the rest for their bar and four numbers.
+pub const LATENCY_WIDTH: u16 = 16;
+
+/// How a column shows numbers. Text fields such as `status` are always shown as they are.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnFormat {
+  /// As HAProxy reports them.
+  #[default]
+  Plain,
+  /// A number of bytes, e.g. `1.2 MiB`.
+  Bytes,
+  /// Bytes per second, e.g. `1.2 MiB/s`.
+  BytesRate,
+  /// Anything per second, e.g. `4.2k/s`.
+  Rate,
+  /// A duration in milliseconds, e.g. `12 ms`.
+  Millis,
+  /// A duration in seconds, e.g. `4m 10s`.
+  Seconds,
+  /// A percentage, e.g. `2.5%`.
+  Percent,
+}
+
+/// One column of the main table, as configured under `columns`. The name column always comes first and isn't
+/// configurable.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Column {
+  pub title: String,
+  /// A `show stat` field such as `scur` or `check_status`, `rate.<name>` for a rate worked out by h8r, `latency`
+  /// for the latency bar, `latency_times` for the averages behind it, `latency_max` for the maxima, or `type` for
+  /// the kind of row.
+  pub field: String,
+  #[serde(default)]
+  pub width: Option<u16>,
+  #[serde(default)]
+  pub format: ColumnFormat,
+  /// Left out of the table until switched on in the column picker.
+  #[serde(default)]
+  pub hidden: bool,
+}
+
+/// Value of one cell as far as sorting is concerned. Numbers sort numerically, with missing ones first.
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum SortKey {
+  Text(String),
+  Number(Option<f64>),
+}
+
+/// `stat`'s fields by name for `ColumnData`. Converting a stat isn't cheap, so callers do it once per refresh
+/// rather than every time the rows are rebuilt.
+pub fn stat_fields(stat: &HaproxyStat) -> JsonValue {
+  serde_json::to_value(stat).unwrap_or_default()
+}
+
+/// Everything a column can show for one frontend, backend or server.
+pub struct ColumnData<'a> {
+  pub stat: &'a HaproxyStat,
+  /// `stat_fields` of `stat`.
+  fields: &'a JsonValue,
+  pub rates: Rates,
+  /// Milliseconds that fill a whole latency bar, the same for every row so that bars can be compared.
+  pub latency_scale: i64,
+}
+
+impl<'a> ColumnData<'a> {
+  pub fn new(stat: &'a HaproxyStat, fields: &'a JsonValue, rates: Rates, latency_scale: i64) -> Self {
+    Self { stat, fields, rates, latency_scale }
+  }
+
+  /// The server name for servers, the proxy name otherwise.
+  pub fn name(&self) -> &str {
+    let name = match self.stat.resource_type {
+      Some(2) => &self.stat.svname,
+      _ => &self.stat.pxname,
+    };
+    name.as_deref().unwrap_or_default()
+  }
+
+  /// `field` of a text field such as `status`, `type` for the kind of row, or `name`. Columns without a field in
+  /// `HaproxyStat` are looked up in `extra`, where anything that isn't a number counts as text.
+  pub fn text(&self, field: &str) -> Option<String> {
+    match field {
+      "type" => Some(kind(self.stat).to_string()),
+      "name" => Some(self.name().to_string()),
+      field => match self.fields.get(field) {
+        Some(value) => value.as_str().map(str::to_string),
+        None => self.extra(field).filter(|value| value.parse::<f64>().is_err()).map(str::to_string),
+      },
+    }
+  }
+
+  /// `field` of a numeric field, or `rate.<name>` for a rate.
+  pub fn number(&self, field: &str) -> Option<f64> {
+    match field.strip_prefix("rate.") {
+      Some(name) => rate(&self.rates, name),
+      None => match self.fields.get(field) {
+        Some(value) => value.as_f64(),
+        None => self.extra(field)?.parse().ok(),
+      },
+    }
+  }
+
+  fn extra(&self, field: &str) -> Option<&str> {
+    self.stat.extra.get(field).map(String::as_str).filter(|value| !value.is_empty())
+  }
+}
+
+/// Whether `field` is something `ColumnData::text` or `ColumnData::number` knows about, given the columns the
+/// source reports on top of `HaproxyStat`'s fields.
+pub fn is_field(field: &str, extra_fields: &BTreeSet<String>) -> bool {
+  let known = || serde_json::to_value(HaproxyStat::default()).is_ok_and(|stat| stat.get(field).is_some());
+  match field.strip_prefix("rate.") {
+    Some(name) => RATES.contains(&name),
+    None => ["type", "name"].contains(&field) || extra_fields.contains(field) || known(),
+  }
+}
+
+/// `Frontend`, `Backend` or `Server`, going by HAProxy's numeric `type` field.
+pub fn kind(stat: &HaproxyStat) -> &'static str {
+  match stat.resource_type {
+    Some(0) => "Frontend",
+    Some(1) => "Backend",
+    Some(2) => "Server",
+    _ => "",
+  }
+}
+
+/// The rates available as `rate.<name>`.
+const RATES: [&str; 9] = [
+  "requests", "bytes_in", "bytes_out", "errors", "http_4xx", "http_5xx", "http_responses", "ratio_4xx", "ratio_5xx",
+];
+
+fn rate(rates: &Rates, name: &str) -> Option<f64> {
+  match name {
+    "requests" => rates.requests,
+    "bytes_in" => rates.bytes_in,
+    "bytes_out" => rates.bytes_out,
+    "errors" => rates.errors,
+    "http_4xx" => rates.http_4xx,
+    "http_5xx" => rates.http_5xx,
+    "http_responses" => rates.http_responses,
+    "ratio_4xx" => rates.ratio_4xx(),
+    "ratio_5xx" => rates.ratio_5xx(),
+    _ => None,
+  }
+}
+
+impl Column {
+  pub fn width(&self) -> u16 {
+    self.width.unwrap_or(match self.field.as_str() {
+      "latency" | "latency_times" | "latency_max" => LATENCY_WIDTH,
+      _ => COLUMN_WIDTH,
+    })
+  }
+
+  fn text(&self, data: &ColumnData) -> Option<String> {
+    data.text(&self.field)
+  }
+
+  fn number(&self, data: &ColumnData) -> Option<f64> {
+    match self.field.as_str() {
+      "latency" | "latency_times" => data.stat.ttime.map(|ttime| ttime as f64),
+      "latency_max" => data.stat.ttime_max.map(|ttime| ttime as f64),
+      field => data.number(field),
+    }
+  }
+
+  fn format_number(&self, number: Option<f64>) -> String {
+    match self.format {
+      ColumnFormat::Plain => number.map_or("-".to_string(), |number| number.to_string()),
+      ColumnFormat::Bytes => format_bytes(number),
+      ColumnFormat::BytesRate => format_bytes_rate(number),
+      ColumnFormat::Rate => format_rate(number),
+      ColumnFormat::Millis => millis(&number.map(|number| number as i64)),
+      ColumnFormat::Seconds => seconds(&number.map(|number| number as i64)),
+      ColumnFormat::Percent => format_percent(number),
+    }
+  }
+
+  pub fn sort_key(&self, data: &ColumnData) -> SortKey {
+    match self.text(data) {
+      Some(text) => SortKey::Text(text),
+      None => SortKey::Number(self.number(data)),
+    }
+  }
+
+  pub fn cell(&self, data: &ColumnData) -> Cell<'static> {
+    match self.field.as_str() {
+      "latency" => Cell::from(latency_bar(&Latency::of(data.stat), data.latency_scale, self.width())),
+      "latency_times" => Cell::from(averages(&Latency::of(data.stat))),
+      "latency_max" => Cell::from(maxima(&Latency::of(data.stat))),
+      _ => match self.text(data) {
+        Some(text) => Cell::from(text),
+        None => Cell::from(self.format_number(self.number(data))),
+      },
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn column(field: &str, format: ColumnFormat) -> Column {
+    Column { title: field.to_string(), field: field.to_string(), width: None, format, hidden: false }
+  }
+
+  #[test]
+  fn test_column_values() {
+    let stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let app1 = stats.iter().find(|stat| stat.svname.as_deref() == Some("app1")).unwrap();
+    let rates = Rates { bytes_in: Some(2048.0), http_5xx: Some(1.0), http_responses: Some(4.0), ..Default::default() };
+    let fields = stat_fields(app1);
+    let data = ColumnData::new(app1, &fields, rates, 100);
+
+    let text = |column: Column| column.format_number(column.number(&data));
+    assert_eq!(text(column("qtime", ColumnFormat::Plain)), "0");
+    assert_eq!(text(column("rtime", ColumnFormat::Millis)), "12 ms");
+    assert_eq!(text(column("rate.bytes_in", ColumnFormat::BytesRate)), "2.0 KiB/s");
+    assert_eq!(text(column("rate.ratio_5xx", ColumnFormat::Percent)), "25.0%");
+    assert_eq!(text(column("no_such_field", ColumnFormat::Plain)), "-");
+
+    assert_eq!(column("status", ColumnFormat::Plain).sort_key(&data), SortKey::Text("UP".to_string()));
+    assert_eq!(column("type", ColumnFormat::Plain).sort_key(&data), SortKey::Text("Server".to_string()));
+    assert_eq!(column("latency", ColumnFormat::Plain).sort_key(&data), SortKey::Number(Some(15.0)));
+    assert_eq!(column("latency_max", ColumnFormat::Plain).sort_key(&data), SortKey::Number(Some(230.0)));
+    assert_eq!(column("latency_max", ColumnFormat::Plain).cell(&data), Cell::from("0/3/210/230"));
+  }
+
+  #[test]
+  fn test_extra_fields() {
+    let mut stat = HaproxyStat::default();
+    stat.extra.insert("h3_open".to_string(), "12".to_string());
+    stat.extra.insert("quic_state".to_string(), "ready".to_string());
+    stat.extra.insert("empty".to_string(), "".to_string());
+    let fields = stat_fields(&stat);
+    let data = ColumnData::new(&stat, &fields, Rates::default(), 0);
+
+    assert_eq!(data.number("h3_open"), Some(12.0));
+    assert_eq!(data.text("h3_open"), None);
+    assert_eq!(data.text("quic_state"), Some("ready".to_string()));
+    assert_eq!(data.number("quic_state"), None);
+    assert_eq!((data.text("empty"), data.number("empty")), (None, None));
+    assert_eq!(column("h3_open", ColumnFormat::Plain).sort_key(&data), SortKey::Number(Some(12.0)));
+  }
+
+  #[test]
+  fn test_is_field() {
+    let extra_fields = BTreeSet::from(["h3_open".to_string()]);
+    assert!(is_field("scur", &BTreeSet::new()));
+    assert!(is_field("check_status", &BTreeSet::new()));
+    assert!(is_field("rate.ratio_5xx", &BTreeSet::new()));
+    assert!(is_field("name", &BTreeSet::new()));
+    assert!(is_field("h3_open", &extra_fields));
+    assert!(!is_field("h3_open", &BTreeSet::new()));
+    assert!(!is_field("scru", &extra_fields));
+    assert!(!is_field("rate.nope", &BTreeSet::new()));
+  }
+
+  #[test]
+  fn test_width() {
+    assert_eq!(column("scur", ColumnFormat::Plain).width(), COLUMN_WIDTH);
+    assert_eq!(column("latency", ColumnFormat::Plain).width(), LATENCY_WIDTH);
+    assert_eq!(column("latency_max", ColumnFormat::Plain).width(), LATENCY_WIDTH);
+    assert_eq!(Column { width: Some(4), ..column("latency", ColumnFormat::Plain) }.width(), 4);
+  }
+}
@@ -2,22 +2,22 @@ use crate::action::Action;
 use crate::components::Component;
 use crate::components::Rect;
 use crate::config::Config;
+use crate::stats::{collector::StatsSource, socket::Socket};
 use crate::tui::Frame;
 use ansi_to_tui::IntoText;
-use color_eyre::eyre::{Error, Result};
+use color_eyre::eyre::{eyre, Error, Result};
 use ratatui::layout::Direction;
 use ratatui::layout::Constraint;
 use ratatui::layout::Layout;
 use ratatui::widgets::Block;
 use ratatui::widgets::Borders;
 use ratatui::widgets::Paragraph;
-use std::io::{Read, Write};
-use std::os::unix::net::UnixStream;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::{SyntaxDefinition, SyntaxSet, SyntaxSetBuilder};
 use syntect::util::as_24_bit_terminal_escaped;
 use std::time::Instant;
+use tokio::sync::mpsc::UnboundedSender;
 
 const HAPROXY_SYNTAX: &str = include_str!("../../.config/haproxy.sublime-syntax");
 
@@ -28,6 +28,71 @@ struct HaproxyConfigSnippets {
     acl: Vec<String>,
 }
 
+/// Where a frontend sends traffic, from its section of the config.
+#[derive(Debug, Default, PartialEq)]
+struct FrontendRoutes {
+  /// Indexes of the `use_backend` and `default_backend` lines.
+  lines: Vec<usize>,
+  /// Indexes of the `acl` lines those routing rules refer to.
+  acls: Vec<usize>,
+  /// Every backend the frontend can pick, in config order.
+  backends: Vec<String>,
+}
+
+/// Whether the first two words of `line` are `keyword` and `name`, e.g. `backend app` but not `backend app2`.
+fn declares(line: &str, keyword: &str, name: &str) -> bool {
+  let mut words = line.split_whitespace();
+  words.next() == Some(keyword) && words.next() == Some(name)
+}
+
+/// Finds the routing rules of a `frontend` (or `listen`) section.
+fn frontend_routes(config: &[String], frontend: &str) -> FrontendRoutes {
+  let mut routes = FrontendRoutes::default();
+  let start = config.iter().position(|line| {
+    let mut words = line.split_whitespace();
+    matches!(words.next(), Some("frontend") | Some("listen")) && words.next() == Some(frontend)
+  });
+  let start = match start {
+    Some(start) => start,
+    None => return routes,
+  };
+  // The section ends where the next one starts, i.e. at the next unindented keyword
+  let section = config
+    .iter()
+    .enumerate()
+    .skip(start + 1)
+    .take_while(|(_, line)| line.is_empty() || line.starts_with(char::is_whitespace) || line.starts_with('#'));
+
+  let mut acl_names = vec![];
+  let mut acl_lines = vec![];
+  for (i, line) in section {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+      ["use_backend" | "default_backend", backend, condition @ ..] => {
+        routes.lines.push(i);
+        if !routes.backends.iter().any(|known| known == backend) {
+          routes.backends.push(backend.to_string());
+        }
+        let names = condition.iter().skip_while(|word| !matches!(**word, "if" | "unless")).skip(1);
+        acl_names.extend(names.map(|name| name.trim_start_matches('!').to_string()));
+      },
+      ["acl", name, ..] => acl_lines.push((i, name.to_string())),
+      _ => {},
+    }
+  }
+  routes.acls = acl_lines.into_iter().filter(|(_, name)| acl_names.contains(name)).map(|(i, _)| i).collect();
+  routes
+}
+
+/// The PID from `show info` output.
+fn parse_pid(info: &str) -> Result<u32, String> {
+  let pid = info.lines().find_map(|line| line.strip_prefix("Pid:")).map(str::trim);
+  match pid {
+    Some(pid) => pid.parse().map_err(|_| format!("Invalid Pid in show info: {}", pid)),
+    None => Err("No Pid line in HAProxy's show info".to_string()),
+  }
+}
+
 enum HaproxyDisplay {
     Lines(HaproxyConfigSnippets),
     Error(String),
@@ -36,12 +101,19 @@ enum HaproxyDisplay {
 #[derive(Debug, Default)]
 pub struct ConfigView {
   config: Config,
+  command_tx: Option<UnboundedSender<Action>>,
   pid: Option<u32>,
+  /// Why HAProxy couldn't be asked for its PID, asked again the next time the view opens.
+  pid_error: Option<String>,
   config_path: Option<String>,
   haproxy_config: Option<Vec<String>>,
   highlighted_config: Option<Vec<String>>,
   haproxy_parse_error: Option<Box<Error>>,
   selected_backend: Option<String>,
+  /// When set, only this server's line of the selected backend is shown.
+  selected_server: Option<String>,
+  /// When set, the routing rules of this frontend are shown instead of a backend.
+  selected_frontend: Option<String>,
   parsed_snippets: Option<HaproxyConfigSnippets>,
 }
 
@@ -90,7 +162,7 @@ impl ConfigView {
     
     // If the config is a directory, concatenate all files in alphabetic order, otherwise if
     // it's a file, read it directly
-    let content = match std::fs::read_dir(&self.config_path.as_ref().unwrap()) {
+    let content = match std::fs::read_dir(self.config_path.as_ref().unwrap()) {
       Ok(entries) => {
         log::debug!("ConfigView::read_config: Reading directory");
         let mut content = String::new();
@@ -106,7 +178,7 @@ impl ConfigView {
       },
       Err(_) => {
         log::debug!("ConfigView::read_config: Reading single file");
-        std::fs::read_to_string(&self.config_path.as_ref().unwrap())?
+        std::fs::read_to_string(self.config_path.as_ref().unwrap())?
       },
     };
 
@@ -137,9 +209,7 @@ impl ConfigView {
       .split("\n")
       .map(|line| {
         let ranges = h.highlight_line(line, &ps).unwrap();
-        let escaped = as_24_bit_terminal_escaped(&ranges[..], true);
-
-        escaped
+        as_24_bit_terminal_escaped(&ranges[..], true)
       })
       .collect();
 
@@ -160,8 +230,19 @@ impl ConfigView {
     
     if let Some(ref config) = self.haproxy_config {
       log::debug!("ConfigView::parse_config_snippets: Config has {} lines", config.len());
-      
-      if let Some(ref selected_backend) = self.selected_backend {
+
+      if let Some(ref selected_frontend) = self.selected_frontend {
+        let routes = frontend_routes(config, selected_frontend);
+        log::debug!("ConfigView::parse_config_snippets: Frontend {} routes to {:?}",
+                   selected_frontend, routes.backends);
+
+        let highlighted = self.highlighted_config.as_ref().unwrap();
+        self.parsed_snippets = Some(HaproxyConfigSnippets {
+          frontend: routes.lines.iter().map(|i| highlighted[*i].clone()).collect(),
+          backend: routes.backends,
+          acl: routes.acls.iter().map(|i| highlighted[*i].clone()).collect(),
+        });
+      } else if let Some(ref selected_backend) = self.selected_backend {
         log::debug!("ConfigView::parse_config_snippets: Parsing for backend: {}", selected_backend);
         
         // find the backend section for current backend
@@ -174,16 +255,15 @@ impl ConfigView {
         let mut backend_lines = vec![];
 
         let backend_search_start = Instant::now();
-        for i in 0..config.len() {
-          let line = &config[i];
-          if line.starts_with("backend") && line.contains(selected_backend) {
+        for (i, line) in config.iter().enumerate() {
+          if declares(line, "backend", selected_backend) {
             backend = Some(selected_backend);
             backend_lines.push(self.highlighted_config.as_ref().unwrap()[i].clone());
             log::debug!("ConfigView::parse_config_snippets: Found backend section at line {}", i);
             continue;
           }
 
-          if let Some(ref backend) = backend {
+          if let Some(backend) = backend {
             if line.starts_with("backend") {
               log::debug!("ConfigView::parse_config_snippets: End of backend section at line {}", i);
               break;
@@ -194,19 +274,42 @@ impl ConfigView {
         log::debug!("ConfigView::parse_config_snippets: Backend search took: {:?}, found {} lines", 
                    backend_search_start.elapsed(), backend_lines.len());
 
+        if let Some(ref selected_server) = self.selected_server {
+          // Keep the section header, defaults for its servers and the server's own line
+          let server_lines: Vec<String> = config
+            .iter()
+            .zip(self.highlighted_config.as_ref().unwrap())
+            .skip_while(|(line, _)| !declares(line, "backend", selected_backend))
+            .enumerate()
+            .take_while(|(i, (line, _))| *i == 0 || !line.starts_with("backend"))
+            .filter(|(i, (line, _))| {
+              let mut words = line.split_whitespace();
+              match words.next() {
+                Some("server") => words.next() == Some(selected_server.as_str()),
+                Some("default-server") => true,
+                _ => *i == 0,
+              }
+            })
+            .map(|(_, (_, highlighted))| highlighted.clone())
+            .collect();
+          log::debug!("ConfigView::parse_config_snippets: Found {} lines for server {}",
+                     server_lines.len(), selected_server);
+
+          self.parsed_snippets = Some(HaproxyConfigSnippets { frontend: vec![], backend: server_lines, acl: vec![] });
+          log::debug!("ConfigView::parse_config_snippets: Total parsing took: {:?}", start.elapsed());
+          return Ok(());
+        }
+
         // find use_backend matching our backend
         let use_backend_search_start = Instant::now();
         let mut use_backend = None;
         let mut use_backend_highlighted = None;
-        for i in 0..config.len() {
-          let line = &config[i];
-          if line.contains("use_backend") {
-            if line.contains(selected_backend) {
-              use_backend = Some(line.to_string());
-              use_backend_highlighted = Some(self.highlighted_config.as_ref().unwrap()[i].clone());
-              log::debug!("ConfigView::parse_config_snippets: Found use_backend at line {}: {}", i, line.trim());
-              break;
-            }
+        for (i, line) in config.iter().enumerate() {
+          if declares(line, "use_backend", selected_backend) {
+            use_backend = Some(line.to_string());
+            use_backend_highlighted = Some(self.highlighted_config.as_ref().unwrap()[i].clone());
+            log::debug!("ConfigView::parse_config_snippets: Found use_backend at line {}: {}", i, line.trim());
+            break;
           }
         }
         log::debug!("ConfigView::parse_config_snippets: use_backend search took: {:?}", use_backend_search_start.elapsed());
@@ -218,23 +321,17 @@ impl ConfigView {
         let mut acls = vec![];
         if let Some(ref use_backend) = use_backend {
           let parts: Vec<&str> = use_backend.split_whitespace().collect();
-          for i in 0..parts.len() {
-            if parts[i] == "if" {
-              for j in i+1..parts.len() {
-                acls.push(parts[j].to_string());
-              }
-              break;
-            }
+          if let Some(i) = parts.iter().position(|part| *part == "if") {
+            acls.extend(parts[i + 1..].iter().map(|part| part.to_string()));
           }
         }
         log::debug!("ConfigView::parse_config_snippets: Found ACLs: {:?}", acls);
 
         // find acl lines matching our acls
         let mut acl_lines = vec![];
-        for i in 0..config.len() {
-          let line = &config[i];
+        for (i, line) in config.iter().enumerate() {
           for acl in &acls {
-            if line.trim().starts_with(format!("acl {}", acl).as_str()) {
+            if declares(line, "acl", acl) {
               acl_lines.push(self.highlighted_config.as_ref().unwrap()[i].clone());
               log::debug!("ConfigView::parse_config_snippets: Found ACL definition at line {}: {}", i, line.trim());
             }
@@ -297,46 +394,45 @@ impl Component for ConfigView {
     use std::time::Instant;
     let start = Instant::now();
     log::debug!("ConfigView::init: Starting lightweight initialization");
-    
+    if self.pid.is_some() || self.haproxy_parse_error.is_some() {
+      return Ok(());
+    }
+
     let socket_path = &self.config.paths.socket;
     log::debug!("ConfigView::init: Connecting to socket: {}", socket_path);
 
-    let mut stream = UnixStream::connect(socket_path)?;
-
-    loop {
-      match stream.write(b"show info\n") {
-        Ok(_) => {
-          log::debug!("ConfigView::init: Querying info");
-        },
-        Err(e) => {
-          log::error!("ConfigView::init: Socket write error: {}", e);
-          println!("Error: {}", e);
-        },
-      }
+    let socket = match socket_path.parse() {
+      Ok(StatsSource::Socket(address)) if address.is_local() => Socket::from_address(address),
+      Ok(StatsSource::Socket(_)) => {
+        // The PID a remote HAProxy reports would be looked up among this machine's processes
+        log::warn!("ConfigView::init: {} is not on this machine, skipping config lookup", socket_path);
+        self.haproxy_parse_error = Some(Box::new(eyre!("Config unavailable for remote endpoints")));
+        return Ok(());
+      },
+      Err(e) => {
+        log::error!("ConfigView::init: Invalid socket {}: {}", socket_path, e);
+        self.haproxy_parse_error = Some(Box::new(e.into()));
+        return Ok(());
+      },
+      Ok(StatsSource::Http(_)) => {
+        log::warn!("ConfigView::init: HTTP stats source has no CLI, skipping PID lookup");
+        self.haproxy_parse_error = Some(Box::new(eyre!("Config lookup requires a stats socket, not the HTTP stats page")));
+        return Ok(());
+      },
+    };
 
-      let mut resp = String::new();
+    let tx = match &self.command_tx {
+      Some(tx) => tx.clone(),
+      None => return Ok(()),
+    };
+    let socket_path = socket_path.clone();
+    // show info waits for the shared connection behind the collector's show stat, so it can't run on the UI thread
+    tokio::task::spawn_blocking(move || {
       let read_start = Instant::now();
-      stream.read_to_string(&mut resp)?;
-      log::debug!("ConfigView::init: Socket read took: {:?}, response size: {} bytes", 
-                 read_start.elapsed(), resp.len());
-
-      // find line that begins with Pid:
-      let pid_line = resp.lines().find(|line| line.starts_with("Pid:"));
-
-      if let Some(pid_line) = pid_line {
-        let pid = pid_line.split(":").last().unwrap().trim();
-        self.pid = Some(pid.parse().unwrap());
-        log::debug!("ConfigView::init: Found HAProxy PID: {}", pid);
-        
-        // Don't do expensive config parsing here - let it happen later
-        log::debug!("ConfigView::init: Deferring config parsing to allow UI to render");
-        break;
-      } else {
-        log::warn!("ConfigView::init: No Pid line found in HAProxy info response");
-      }
-
-      break;
-    }
+      let pid = socket.query("show info").map_err(|e| e.to_string()).and_then(|resp| parse_pid(&resp));
+      log::debug!("ConfigView::init: show info took {:?}, PID: {:?}", read_start.elapsed(), pid);
+      let _ = tx.send(Action::HaproxyPid(socket_path, pid));
+    });
 
     log::debug!("ConfigView::init: Lightweight initialization took: {:?}", start.elapsed());
     Ok(())
@@ -360,12 +456,13 @@ impl Component for ConfigView {
           acl: snippets.acl.clone(),
         })
       },
-      None => match self.haproxy_parse_error {
-        Some(ref e) => {
+      None => match (&self.haproxy_parse_error, &self.pid_error) {
+        (Some(e), _) => {
           log::trace!("ConfigView::draw: Displaying error: {}", e);
           HaproxyDisplay::Error(format!("Error: {}", e))
         },
-        None => {
+        (None, Some(e)) => HaproxyDisplay::Error(format!("Error: Can't ask HAProxy for its PID: {}", e)),
+        (None, None) => {
           log::trace!("ConfigView::draw: Displaying loading message");
           HaproxyDisplay::Error("Loading...".to_string())
         },
@@ -374,12 +471,14 @@ impl Component for ConfigView {
 
     match content {
       HaproxyDisplay::Lines(snippets) => {
-        let frontend_size = snippets.frontend.len();
-        let acl_size = snippets.acl.len();
+        // Panels without anything to show (e.g. for a server) are left out entirely
+        let panel_size = |lines: &Vec<String>| if lines.is_empty() { 0 } else { lines.len() as u16 + 2 };
+        let frontend_size = panel_size(&snippets.frontend);
+        let acl_size = panel_size(&snippets.acl);
 
         let code_layout = Layout::default()
           .direction(Direction::Vertical)
-          .constraints(vec![Constraint::Length(frontend_size as u16 + 2), Constraint::Length(acl_size as u16 + 2), Constraint::Min(0)])
+          .constraints(vec![Constraint::Length(frontend_size), Constraint::Length(acl_size), Constraint::Min(0)])
           .split(rect);
 
         let frontend_frame = code_layout[0];
@@ -387,16 +486,22 @@ impl Component for ConfigView {
         let backend_frame = code_layout[2];
 
         let render_start = Instant::now();
-        let frontend = Paragraph::new(snippets.frontend.join("\n").into_text()?)
-          .block(Block::default().borders(Borders::ALL).title("Frontend"));
-        f.render_widget(frontend, frontend_frame);
+        if frontend_size > 0 {
+          let title = if self.selected_frontend.is_some() { "Routing" } else { "Frontend" };
+          let frontend = Paragraph::new(snippets.frontend.join("\n").into_text()?)
+            .block(Block::default().borders(Borders::ALL).title(title));
+          f.render_widget(frontend, frontend_frame);
+        }
 
-        let acl = Paragraph::new(snippets.acl.join("\n").into_text()?)
-          .block(Block::default().borders(Borders::ALL).title("Acls"));
-        f.render_widget(acl, acl_frame);
+        if acl_size > 0 {
+          let acl = Paragraph::new(snippets.acl.join("\n").into_text()?)
+            .block(Block::default().borders(Borders::ALL).title("Acls"));
+          f.render_widget(acl, acl_frame);
+        }
 
+        let title = if self.selected_frontend.is_some() { "Backends" } else { "Backend" };
         let backend = Paragraph::new(snippets.backend.join("\n").into_text()?)
-          .block(Block::default().borders(Borders::ALL).title("Backend"));
+          .block(Block::default().borders(Borders::ALL).title(title));
         f.render_widget(backend, backend_frame);
         
         log::trace!("ConfigView::draw: Widget rendering took: {:?}", render_start.elapsed());
@@ -417,8 +522,23 @@ impl Component for ConfigView {
     Ok(())
   }
 
+  fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+    self.command_tx = Some(tx);
+    Ok(())
+  }
+
   fn register_config_handler(&mut self, config: Config) -> Result<()> {
     log::debug!("ConfigView::register_config_handler: Registering config");
+    if config.paths.socket != self.config.paths.socket {
+      log::debug!("ConfigView::register_config_handler: Socket changed, dropping cached config");
+      *self = ConfigView {
+        selected_backend: self.selected_backend.take(),
+        selected_server: self.selected_server.take(),
+        selected_frontend: self.selected_frontend.take(),
+        command_tx: self.command_tx.take(),
+        ..Default::default()
+      };
+    }
     self.config = config;
     Ok(())
   }
@@ -426,9 +546,41 @@ impl Component for ConfigView {
   fn update(&mut self, action: Action) -> Result<Option<Action>> {
     log::trace!("ConfigView::update: Received action: {:?}", action);
     match action {
+      Action::HaproxyPid(socket, pid) if socket == self.config.paths.socket => {
+        match pid {
+          Ok(pid) => {
+            log::debug!("ConfigView::update: Found HAProxy PID: {}", pid);
+            self.pid = Some(pid);
+            self.pid_error = None;
+          },
+          Err(e) => {
+            log::warn!("ConfigView::update: Can't get HAProxy's PID: {}", e);
+            self.pid_error = Some(e);
+          },
+        }
+        Ok(None)
+      },
       Action::UseItem(backend_name) => {
         log::info!("ConfigView::update: Switching to backend: {}", backend_name);
         self.selected_backend = Some(backend_name);
+        self.selected_server = None;
+        self.selected_frontend = None;
+        self.parse_config_snippets()?;
+        Ok(None)
+      },
+      Action::UseServer(backend_name, server_name) => {
+        log::info!("ConfigView::update: Switching to server: {}/{}", backend_name, server_name);
+        self.selected_backend = Some(backend_name);
+        self.selected_server = Some(server_name);
+        self.selected_frontend = None;
+        self.parse_config_snippets()?;
+        Ok(None)
+      },
+      Action::UseFrontend(frontend_name) => {
+        log::info!("ConfigView::update: Switching to frontend: {}", frontend_name);
+        self.selected_frontend = Some(frontend_name);
+        self.selected_backend = None;
+        self.selected_server = None;
         self.parse_config_snippets()?;
         Ok(None)
       },
@@ -439,3 +591,105 @@ impl Component for ConfigView {
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_parse_pid() {
+    assert_eq!(parse_pid("Name: HAProxy\nVersion: 2.8.3\nPid: 1271\nUptime: 0d 0h02m07s\n"), Ok(1271));
+    assert!(parse_pid("Name: HAProxy\n").is_err());
+    assert!(parse_pid("Pid: soon\n").is_err());
+  }
+
+  #[test]
+  fn test_init_without_haproxy() {
+    let mut config = Config::default();
+    config.paths.socket = "tcp:no-port".to_string();
+    let mut view = ConfigView::default();
+    view.register_config_handler(config).unwrap();
+
+    // A bad or unreachable socket is shown in the view instead of ending the app
+    assert!(view.init(Rect::default()).is_ok());
+    assert!(view.haproxy_parse_error.is_some());
+  }
+
+  #[test]
+  fn test_init_remote_endpoint() {
+    let mut config = Config::default();
+    config.paths.socket = "tcp:10.0.0.5:9999".to_string();
+    let mut view = ConfigView::default();
+    view.register_config_handler(config).unwrap();
+
+    assert!(view.init(Rect::default()).is_ok());
+    assert_eq!(view.haproxy_parse_error.unwrap().to_string(), "Config unavailable for remote endpoints");
+  }
+
+  #[test]
+  fn test_backend_snippets() {
+    let config: Vec<String> = r#"
+frontend web
+    acl is_api path_beg /api
+    acl is_api2 path_beg /api2
+    use_backend app2 if is_api2
+    use_backend app if is_api
+
+backend app2
+    server app21 10.0.0.21:8080
+
+backend app
+    server app1 10.0.0.11:8080
+    server app10 10.0.0.10:8080
+"#
+    .lines()
+    .map(str::to_string)
+    .collect();
+
+    let mut view =
+      ConfigView { haproxy_config: Some(config.clone()), highlighted_config: Some(config), ..Default::default() };
+    view.update(Action::UseItem("app".to_string())).unwrap();
+    let snippets = view.parsed_snippets.as_ref().unwrap();
+    assert_eq!(snippets.frontend, vec!["    use_backend app if is_api"]);
+    assert_eq!(snippets.acl, vec!["    acl is_api path_beg /api"]);
+    assert_eq!(snippets.backend[0], "backend app");
+
+    view.update(Action::UseServer("app".to_string(), "app1".to_string())).unwrap();
+    let snippets = view.parsed_snippets.as_ref().unwrap();
+    assert_eq!(snippets.backend, vec!["backend app", "    server app1 10.0.0.11:8080"]);
+  }
+
+  #[test]
+  fn test_frontend_routes() {
+    let config: Vec<String> = r#"
+frontend web
+    bind :80
+    acl is_api path_beg /api
+    acl is_static path_end .css
+    acl unused hdr(host) -i old.example.com
+    use_backend api if is_api !is_static
+    use_backend static if is_static
+    default_backend app
+
+backend app
+    server app1 10.0.0.11:8080
+
+listen stats
+    bind :8404
+    use_backend api
+"#
+    .lines()
+    .map(str::to_string)
+    .collect();
+
+    let routes = frontend_routes(&config, "web");
+    assert_eq!(routes.backends, vec!["api", "static", "app"]);
+    assert_eq!(routes.lines, vec![6, 7, 8]);
+    assert_eq!(routes.acls, vec![3, 4]);
+
+    assert_eq!(frontend_routes(&config, "stats").backends, vec!["api"]);
+    assert_eq!(frontend_routes(&config, "missing"), FrontendRoutes::default());
+  }
+}
@@ -0,0 +1,104 @@
+use std::fmt::Display;
+
+use ratatui::{prelude::*, widgets::*};
+
+use super::Frame;
+
+/// A titled list of field names and values, one of the boxes of a detail view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+  pub title: &'static str,
+  pub rows: Vec<(&'static str, String)>,
+}
+
+impl Section {
+  pub fn new(title: &'static str, rows: Vec<(&'static str, String)>) -> Self {
+    Self { title, rows }
+  }
+}
+
+/// Formats a stat for display, `-` when HAProxy left it empty.
+pub fn value<T: Display>(value: &Option<T>) -> String {
+  match value {
+    Some(value) => value.to_string(),
+    None => "-".to_string(),
+  }
+}
+
+/// Formats a stat holding milliseconds, e.g. a check duration or average response time.
+pub fn millis(value: &Option<i64>) -> String {
+  match value {
+    Some(value) => format!("{} ms", value),
+    None => "-".to_string(),
+  }
+}
+
+/// Formats a stat holding seconds as e.g. `2d 3h`, `4m 10s` or `12s`.
+pub fn seconds(value: &Option<i64>) -> String {
+  let seconds = match value {
+    Some(seconds) => *seconds,
+    None => return "-".to_string(),
+  };
+  let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+  match (days, hours, minutes) {
+    (0, 0, 0) => format!("{}s", seconds),
+    (0, 0, _) => format!("{}m {}s", minutes, seconds % 60),
+    (0, _, _) => format!("{}h {}m", hours, minutes),
+    _ => format!("{}d {}h", days, hours),
+  }
+}
+
+/// Formats a value next to its limit, e.g. `12 / 100`, leaving out a limit HAProxy doesn't report.
+pub fn of_limit<T: Display, L: Display>(current: &Option<T>, limit: &Option<L>) -> String {
+  match limit {
+    Some(limit) => format!("{} / {}", value(current), limit),
+    None => value(current),
+  }
+}
+
+/// Lays `sections` out in rows of up to `per_row` boxes.
+pub fn draw_sections(f: &mut Frame<'_>, area: Rect, sections: &[Section], per_row: usize) {
+  let rows: Vec<&[Section]> = sections.chunks(per_row.max(1)).collect();
+  let areas = Layout::default()
+    .direction(Direction::Vertical)
+    .constraints(rows.iter().map(|row| {
+      Constraint::Length(row.iter().map(|section| section.rows.len()).max().unwrap_or_default() as u16 + 2)
+    }))
+    .split(area);
+
+  for (row, area) in rows.iter().zip(areas.iter()) {
+    let columns = Layout::default()
+      .direction(Direction::Horizontal)
+      .constraints(vec![Constraint::Ratio(1, row.len() as u32); row.len()])
+      .split(*area);
+
+    for (section, area) in row.iter().zip(columns.iter()) {
+      let width = section.rows.iter().map(|(name, _)| name.len()).max().unwrap_or_default() as u16 + 1;
+      let rows =
+        section.rows.iter().map(|(name, value)| Row::new(vec![name.to_string().yellow(), value.clone().white()]));
+      let table = Table::new(rows, [Constraint::Length(width), Constraint::Min(0)])
+        .block(Block::default().borders(Borders::ALL).title(format!(" {} ", section.title)));
+      f.render_widget(table, *area);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_formatters() {
+    assert_eq!(value::<i64>(&None), "-");
+    assert_eq!(value(&Some(1520.0)), "1520");
+    assert_eq!(millis(&Some(3)), "3 ms");
+    assert_eq!(seconds(&Some(42)), "42s");
+    assert_eq!(seconds(&Some(250)), "4m 10s");
+    assert_eq!(seconds(&Some(7500)), "2h 5m");
+    assert_eq!(seconds(&Some(183600)), "2d 3h");
+    assert_eq!(of_limit(&Some(3), &Some(100)), "3 / 100");
+    assert_eq!(of_limit::<i64, i64>(&Some(3), &None), "3");
+  }
+}
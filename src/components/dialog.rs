@@ -0,0 +1,316 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use super::Frame;
+use crate::{
+  action::Action,
+  stats::admin::{AdminCommand, FrontendCommand, ServerAddr, Weight, WorkerResponse, MAX_WEIGHT, MAX_WEIGHT_PERCENT},
+};
+
+/// Something to type in before a command can be sent.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prompt {
+  /// A new weight for a server, along with its current `weight` and `uweight`.
+  Weight { backend: String, server: String, weight: Option<i64>, uweight: Option<i64> },
+  /// A new address and port for a server, along with its current `addr`.
+  Address { backend: String, server: String, addr: Option<String> },
+  /// A new `maxconn` for a frontend, along with its current `slim`.
+  Maxconn { frontend: String, slim: Option<i64> },
+  /// The frontend's name, typed in again before it's shut down for good.
+  Shutdown { frontend: String },
+}
+
+impl Prompt {
+  fn title(&self) -> String {
+    match self {
+      Prompt::Weight { backend, server, .. } => format!("Weight of {}/{}", backend, server),
+      Prompt::Address { backend, server, .. } => format!("Address of {}/{}", backend, server),
+      Prompt::Maxconn { frontend, .. } => format!("Maxconn of {}", frontend),
+      Prompt::Shutdown { frontend } => format!("Shut down {}", frontend),
+    }
+  }
+
+  fn lines(&self) -> Vec<Line<'static>> {
+    let value = |value: &Option<i64>| value.map_or("-".to_string(), |value| value.to_string());
+    match self {
+      Prompt::Weight { weight, uweight, .. } => vec![
+        Line::from(format!("Now {} (user weight {})", value(weight), value(uweight))),
+        Line::from(format!("0 to {}, or 0% to {}% of the initial weight such as 50%", MAX_WEIGHT, MAX_WEIGHT_PERCENT)),
+      ],
+      Prompt::Address { addr, .. } => vec![
+        Line::from(format!("Now {}", addr.as_deref().unwrap_or("-"))),
+        Line::from("An IPv4 or IPv6 address, with :port to change the port too, e.g. [2001:db8::2]:8080"),
+      ],
+      Prompt::Maxconn { slim, .. } => vec![Line::from(format!("Now {}", value(slim)))],
+      Prompt::Shutdown { frontend } => vec![
+        Line::from("This releases the frontend's ports and can't be undone without reloading HAProxy.".red()),
+        Line::from(format!("Type {} to confirm", frontend)),
+      ],
+    }
+  }
+
+  /// The command to send for `input`, or why there's none.
+  pub fn command(&self, input: &str) -> Result<AdminCommand, String> {
+    match self {
+      Prompt::Weight { backend, server, .. } => {
+        Ok(AdminCommand::set_server_weight(backend, server, input.parse::<Weight>()?))
+      },
+      Prompt::Address { backend, server, .. } => {
+        Ok(AdminCommand::set_server_addr(backend, server, input.parse::<ServerAddr>()?))
+      },
+      Prompt::Maxconn { frontend, .. } => match input.trim().parse() {
+        Ok(maxconn) => Ok(AdminCommand::set_frontend_maxconn(frontend, maxconn)),
+        Err(_) => Err(format!("'{}' isn't a number of connections", input.trim())),
+      },
+      Prompt::Shutdown { frontend } if input == frontend => {
+        Ok(AdminCommand::frontend(frontend, FrontendCommand::Shutdown))
+      },
+      Prompt::Shutdown { frontend } => Err(format!("Type {} exactly", frontend)),
+    }
+  }
+}
+
+/// A popup asking before an `AdminCommand` is sent to HAProxy, and then showing what HAProxy answered.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Dialog {
+  /// Waiting for input, typed so far.
+  Prompt(Prompt, String),
+  /// A title and commands to pick one from, along with the highlighted one.
+  Choose(String, Vec<AdminCommand>, usize),
+  Confirm(AdminCommand),
+  /// Sent, waiting for HAProxy to answer.
+  Waiting(AdminCommand),
+  /// What each process answered, or why the command couldn't be sent at all.
+  Response(AdminCommand, Result<Vec<WorkerResponse>, String>),
+}
+
+impl Dialog {
+  /// What a key press leads to: the dialog to show next, if any, and an action to send.
+  pub fn handle_key(&self, key: KeyEvent) -> (Option<Dialog>, Option<Action>) {
+    match (self, key.code) {
+      (Dialog::Prompt(prompt, input), KeyCode::Enter) => match prompt.command(input) {
+        Ok(command) => (Some(Dialog::Waiting(command.clone())), Some(Action::RunCommand(command.command))),
+        Err(_) => (Some(self.clone()), None),
+      },
+      (Dialog::Prompt(..), KeyCode::Esc) => (None, None),
+      (Dialog::Prompt(prompt, input), KeyCode::Backspace) => {
+        let mut input = input.clone();
+        input.pop();
+        (Some(Dialog::Prompt(prompt.clone(), input)), None)
+      },
+      (Dialog::Prompt(prompt, input), KeyCode::Char(c)) => {
+        (Some(Dialog::Prompt(prompt.clone(), format!("{}{}", input, c))), None)
+      },
+      (Dialog::Prompt(..), _) => (Some(self.clone()), None),
+      (Dialog::Choose(title, commands, selected), KeyCode::Up | KeyCode::Char('k')) => {
+        (Some(Dialog::Choose(title.clone(), commands.clone(), selected.saturating_sub(1))), None)
+      },
+      (Dialog::Choose(title, commands, selected), KeyCode::Down | KeyCode::Char('j')) => {
+        let selected = (selected + 1).min(commands.len().saturating_sub(1));
+        (Some(Dialog::Choose(title.clone(), commands.clone(), selected)), None)
+      },
+      (Dialog::Choose(_, commands, selected), KeyCode::Enter) => match commands.get(*selected) {
+        Some(command) => (Some(Dialog::Confirm(command.clone())), None),
+        None => (None, None),
+      },
+      (Dialog::Choose(..), KeyCode::Esc | KeyCode::Char('q')) => (None, None),
+      (Dialog::Choose(..), _) => (Some(self.clone()), None),
+      (Dialog::Confirm(command), KeyCode::Char('y') | KeyCode::Enter) => {
+        (Some(Dialog::Waiting(command.clone())), Some(Action::RunCommand(command.command.clone())))
+      },
+      (Dialog::Confirm(_), KeyCode::Char('n') | KeyCode::Esc) => (None, None),
+      (Dialog::Confirm(_), _) => (Some(self.clone()), None),
+      // Any key closes the dialog once the command is on its way
+      (Dialog::Waiting(_) | Dialog::Response(..), _) => (None, None),
+    }
+  }
+
+  /// Takes in HAProxy's answer if it's for the command this dialog is waiting on.
+  pub fn respond(&mut self, command: &str, response: Result<Vec<WorkerResponse>, String>) {
+    if let Dialog::Waiting(waiting) = self {
+      if waiting.command == command {
+        *self = Dialog::Response(waiting.clone(), response);
+      }
+    }
+  }
+
+  fn lines(&self) -> (Line<'static>, Vec<Line<'static>>) {
+    match self {
+      Dialog::Prompt(prompt, input) => {
+        let mut lines = prompt.lines();
+        lines.extend([Line::default(), Line::from(vec!["> ".yellow(), input.clone().into(), "_".yellow()])]);
+        lines.push(match prompt.command(input) {
+          Ok(command) => Line::from(command.command.dark_gray()),
+          Err(_) if input.trim().is_empty() => Line::default(),
+          Err(error) => Line::from(error.red()),
+        });
+        lines.extend([Line::default(), Line::from("Enter: send, Esc: cancel")]);
+        (prompt.title().yellow().into(), lines)
+      },
+      Dialog::Choose(title, commands, selected) => {
+        let mut lines: Vec<Line<'static>> = commands
+          .iter()
+          .enumerate()
+          .map(|(index, command)| match index == *selected {
+            true => Line::from(format!("> {}", command.description).reversed()),
+            false => Line::from(format!("  {}", command.description)),
+          })
+          .collect();
+        lines.extend([Line::default(), Line::from("Up/Down: pick, Enter: continue, Esc: cancel")]);
+        (title.clone().yellow().into(), lines)
+      },
+      Dialog::Confirm(command) => ("Confirm".yellow().into(), vec![
+        Line::from(format!("{}?", command.description)),
+        Line::from(command.command.clone().dark_gray()),
+        Line::default(),
+        Line::from("y/Enter: send, n/Esc: cancel"),
+      ]),
+      Dialog::Waiting(command) => ("Sending".yellow().into(), vec![
+        Line::from(command.command.clone().dark_gray()),
+        Line::default(),
+        Line::from("Waiting for HAProxy..."),
+      ]),
+      Dialog::Response(command, Ok(responses)) => {
+        let mut lines = vec![Line::from(command.command.clone().dark_gray()), Line::default()];
+        for WorkerResponse { pid, response } in responses {
+          if let Some(pid) = pid {
+            let outcome = if response.is_ok() { "applied".green() } else { "failed".red() };
+            lines.push(Line::from(vec![format!("Worker {}: ", pid).bold(), outcome]));
+          }
+          match response {
+            Ok(text) => lines.extend(text.lines().map(|line| Line::from(line.to_string()))),
+            Err(error) => lines.extend(error.lines().map(|line| Line::from(line.to_string().red()))),
+          }
+        }
+
+        let failed = responses.iter().filter(|response| response.response.is_err()).count();
+        if failed == 0 {
+          lines.push(Line::from("Done, the table catches up with the next refresh.".green()));
+          ("Done".green().into(), lines)
+        } else if failed < responses.len() {
+          let total = responses.len();
+          let message = format!("{} of {} workers failed, so they disagree until it's sent again", failed, total);
+          lines.push(Line::from(message.red()));
+          ("Partly failed".red().into(), lines)
+        } else {
+          ("Failed".red().into(), lines)
+        }
+      },
+      Dialog::Response(command, Err(error)) => {
+        let mut lines = vec![Line::from(command.command.clone().dark_gray()), Line::default()];
+        lines.extend(error.lines().map(|line| Line::from(line.to_string().red())));
+        ("Failed".red().into(), lines)
+      },
+    }
+  }
+
+  pub fn draw(&self, f: &mut Frame<'_>, area: Rect) {
+    let (title, lines) = self.lines();
+    let width = 64.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+
+    let block = Block::bordered().title(title).border_style(Style::default().fg(Color::Yellow));
+    f.render_widget(Clear, popup);
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }).block(block), popup);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crossterm::event::KeyModifiers;
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::stats::admin::ServerState;
+
+  fn text(lines: Vec<Line>) -> Vec<String> {
+    lines.iter().map(|line| line.spans.iter().map(|span| span.content.as_ref()).collect()).collect()
+  }
+
+  fn typed(dialog: Dialog, input: &str) -> Dialog {
+    input.chars().fold(dialog, |dialog, c| dialog.handle_key(key(KeyCode::Char(c))).0.unwrap())
+  }
+
+  fn key(code: KeyCode) -> KeyEvent {
+    KeyEvent::new(code, KeyModifiers::NONE)
+  }
+
+  #[test]
+  fn test_confirm() {
+    let command = AdminCommand::set_server_state("app", "app1", ServerState::Drain);
+    let dialog = Dialog::Confirm(command.clone());
+
+    assert_eq!(dialog.handle_key(key(KeyCode::Char('x'))), (Some(dialog.clone()), None));
+    assert_eq!(dialog.handle_key(key(KeyCode::Esc)), (None, None));
+
+    let (next, action) = dialog.handle_key(key(KeyCode::Char('y')));
+    assert_eq!(action, Some(Action::RunCommand("set server app/app1 state drain".to_string())));
+
+    let mut next = next.unwrap();
+    next.respond("set server app/app2 state drain", Ok(vec![]));
+    assert_eq!(next, Dialog::Waiting(command.clone()));
+    next.respond("set server app/app1 state drain", Err("No such server.".to_string()));
+    assert_eq!(next, Dialog::Response(command, Err("No such server.".to_string())));
+    assert_eq!(next.handle_key(key(KeyCode::Char('y'))), (None, None));
+  }
+
+  #[test]
+  fn test_prompt() {
+    let prompt = Prompt::Weight { backend: "app".into(), server: "app1".into(), weight: Some(1), uweight: Some(1) };
+    let dialog = typed(Dialog::Prompt(prompt.clone(), String::new()), "3000");
+
+    // Not a valid weight, so Enter does nothing
+    assert_eq!(dialog.handle_key(key(KeyCode::Enter)), (Some(dialog.clone()), None));
+
+    let (dialog, _) = dialog.handle_key(key(KeyCode::Backspace));
+    let (dialog, _) = dialog.unwrap().handle_key(key(KeyCode::Backspace));
+    let dialog = typed(dialog.unwrap(), "%");
+    assert_eq!(dialog, Dialog::Prompt(prompt, "30%".to_string()));
+
+    let (next, action) = dialog.handle_key(key(KeyCode::Enter));
+    assert_eq!(action, Some(Action::RunCommand("set server app/app1 weight 30%".to_string())));
+    assert!(matches!(next, Some(Dialog::Waiting(_))));
+    assert_eq!(dialog.handle_key(key(KeyCode::Esc)), (None, None));
+  }
+
+  #[test]
+  fn test_choose() {
+    let commands = AdminCommand::server_checks("app", "app1");
+    let dialog = Dialog::Choose("Checks".to_string(), commands.clone(), 0);
+
+    let (dialog, _) = dialog.handle_key(key(KeyCode::Up));
+    let (dialog, _) = dialog.unwrap().handle_key(key(KeyCode::Down));
+    let (dialog, action) = dialog.unwrap().handle_key(key(KeyCode::Enter));
+    assert_eq!((dialog, action), (Some(Dialog::Confirm(commands[1].clone())), None));
+  }
+
+  #[test]
+  fn test_shutdown_prompt() {
+    let prompt = Prompt::Shutdown { frontend: "web".into() };
+    let dialog = typed(Dialog::Prompt(prompt.clone(), String::new()), "WEB");
+    assert_eq!(dialog.handle_key(key(KeyCode::Enter)).1, None);
+
+    let (_, action) = typed(Dialog::Prompt(prompt, String::new()), "web").handle_key(key(KeyCode::Enter));
+    assert_eq!(action, Some(Action::RunCommand("shutdown frontend web".to_string())));
+  }
+
+  #[test]
+  fn test_response_per_worker() {
+    let command = AdminCommand::set_server_state("app", "app1", ServerState::Drain);
+    let responses = vec![
+      WorkerResponse { pid: Some(1271), response: Ok(String::new()) },
+      WorkerResponse { pid: Some(1272), response: Err("No such server.".to_string()) },
+    ];
+    let (title, lines) = Dialog::Response(command, Ok(responses)).lines();
+    assert_eq!(text(vec![title]), vec!["Partly failed"]);
+    assert_eq!(text(lines), vec![
+      "set server app/app1 state drain",
+      "",
+      "Worker 1271: applied",
+      "Worker 1272: failed",
+      "No such server.",
+      "1 of 2 workers failed, so they disagree until it's sent again",
+    ]);
+  }
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+  details::{draw_sections, of_limit, value, Section},
+  grey_out, Component, Frame,
+};
+use crate::{
+  action::Action,
+  stats::{
+    data::HaproxyStat,
+    history::StatKey,
+    metrics::HaproxyMetrics,
+    rates::{format_bytes_rate, format_rate, Rates},
+  },
+};
+
+/// Traffic, rejections, responses and cache/compression stats of one frontend.
+#[derive(Default)]
+pub struct FrontendDetails {
+  metrics: Option<Arc<HaproxyMetrics>>,
+  frontend: Option<String>,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+/// `current (max peak, limit)`, for HAProxy's own per-second rate fields.
+fn rate_with_max(current: &Option<f64>, max: &Option<f64>, limit: &Option<f64>) -> String {
+  match limit {
+    Some(limit) if *limit > 0.0 => format!("{}/s (max {}, limit {})", value(current), value(max), limit),
+    _ => format!("{}/s (max {})", value(current), value(max)),
+  }
+}
+
+/// `part` as a percentage of `total`, e.g. cache hits out of lookups.
+fn ratio(part: &Option<f64>, total: &Option<f64>) -> String {
+  match (part, total) {
+    (Some(part), Some(total)) if *total > 0.0 => format!("{:.1}%", part / total * 100.0),
+    _ => "-".to_string(),
+  }
+}
+
+fn traffic(stat: &HaproxyStat, rates: Rates) -> Section {
+  Section::new("Frontend", vec![
+    ("Status", value(&stat.status)),
+    ("Mode", value(&stat.mode)),
+    ("Sessions", format!("{} (max {})", of_limit(&stat.scur, &stat.slim), value(&stat.smax))),
+    ("Session rate", rate_with_max(&stat.rate, &stat.rate_max, &stat.rate_lim)),
+    ("Request rate", rate_with_max(&stat.req_rate, &stat.req_rate_max, &None)),
+    ("Conn rate", rate_with_max(&stat.conn_rate, &stat.conn_rate_max, &None)),
+    ("Requests", format!("{} total, {}", value(&stat.req_tot), format_rate(rates.requests))),
+    ("Connections", value(&stat.conn_tot)),
+    ("In", format_bytes_rate(rates.bytes_in)),
+    ("Out", format_bytes_rate(rates.bytes_out)),
+  ])
+}
+
+fn rejections(stat: &HaproxyStat) -> Section {
+  Section::new("Denied & errors", vec![
+    ("Denied requests", value(&stat.dreq)),
+    ("Denied responses", value(&stat.dresp)),
+    ("Denied conns", value(&stat.dcon)),
+    ("Denied sessions", value(&stat.dses)),
+    ("Request errors", value(&stat.ereq)),
+    ("Intercepted", value(&stat.intercepted)),
+    ("Failed rewrites", value(&stat.wrew)),
+    ("Internal errors", value(&stat.eint)),
+  ])
+}
+
+fn responses(stat: &HaproxyStat) -> Section {
+  Section::new("HTTP responses", vec![
+    ("1xx", value(&stat.hrsp_1xx)),
+    ("2xx", value(&stat.hrsp_2xx)),
+    ("3xx", value(&stat.hrsp_3xx)),
+    ("4xx", value(&stat.hrsp_4xx)),
+    ("5xx", value(&stat.hrsp_5xx)),
+    ("Other", value(&stat.hrsp_other)),
+  ])
+}
+
+fn cache_and_compression(stat: &HaproxyStat) -> Section {
+  Section::new("Cache & compression", vec![
+    ("Cache lookups", value(&stat.cache_lookups)),
+    ("Cache hits", format!("{} ({})", value(&stat.cache_hits), ratio(&stat.cache_hits, &stat.cache_lookups))),
+    ("Compressed in", value(&stat.comp_in)),
+    ("Compressed out", format!("{} ({} of input)", value(&stat.comp_out), ratio(&stat.comp_out, &stat.comp_in))),
+    ("Bypassed", value(&stat.comp_byp)),
+    ("Responses", value(&stat.comp_rsp)),
+  ])
+}
+
+/// Sections shown for a frontend, in display order.
+fn sections(stat: &HaproxyStat, rates: Rates) -> Vec<Section> {
+  vec![traffic(stat, rates), rejections(stat), responses(stat), cache_and_compression(stat)]
+}
+
+impl FrontendDetails {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn stat(&self) -> Option<&HaproxyStat> {
+    let instant = self.metrics.as_ref()?.instant.as_ref()?;
+    instant.data.raw.iter().find(|stat| stat.resource_type == Some(0) && stat.pxname == self.frontend)
+  }
+}
+
+impl Component for FrontendDetails {
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::MetricUpdate(metrics) => self.metrics = Some(metrics),
+      Action::UseFrontend(frontend) => {
+        log::info!("FrontendDetails::update: Switching to frontend {}", frontend);
+        self.frontend = Some(frontend);
+      },
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) if self.endpoint.as_ref() == Some(&endpoint) => {
+        self.stale = state.is_stale();
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let title = format!(" {} ", self.frontend.clone().unwrap_or_default());
+    let border = Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)).title(title);
+    let inner = border.inner(area);
+    f.render_widget(border, area);
+
+    match (self.stat(), &self.metrics) {
+      (Some(stat), Some(metrics)) => {
+        let rates = metrics.rates(StatKey::of(stat));
+        draw_sections(f, inner, &sections(stat, rates), 2);
+      },
+      _ => f.render_widget(Paragraph::new("No data for this frontend yet"), inner),
+    }
+
+    if self.stale {
+      grey_out(f, area);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_sections() {
+    let stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(stats).unwrap();
+
+    let mut details = FrontendDetails::new();
+    details.update(Action::MetricUpdate(Arc::new(metrics))).unwrap();
+    details.update(Action::UseFrontend("web".to_string())).unwrap();
+    let web = details.stat().unwrap();
+    assert_eq!(web.svname.as_deref(), Some("FRONTEND"));
+
+    let sections = sections(web, Rates::default());
+    let titles: Vec<&str> = sections.iter().map(|section| section.title).collect();
+    assert_eq!(titles, vec!["Frontend", "Denied & errors", "HTTP responses", "Cache & compression"]);
+    assert_eq!(sections[0].rows[0], ("Status", "OPEN".to_string()));
+  }
+
+  #[test]
+  fn test_ratio() {
+    assert_eq!(ratio(&Some(25.0), &Some(200.0)), "12.5%");
+    assert_eq!(ratio(&Some(25.0), &Some(0.0)), "-");
+    assert_eq!(ratio(&None, &Some(10.0)), "-");
+  }
+}
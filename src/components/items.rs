@@ -1,26 +1,118 @@
 use color_eyre::{eyre::Result, owo_colors::OwoColorize};
+use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{prelude::*, widgets::*};
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::Arc;
+use serde_json::Value as JsonValue;
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{Component, Frame};
+use super::{
+  columns::{stat_fields, Column, ColumnData, SortKey},
+  dialog::{Dialog, Prompt},
+  grey_out,
+  query::Query,
+  Component, Frame,
+};
 use crate::stats::data::StatusType;
 use crate::stats::metrics::HaproxyFrontendStatus;
 use crate::{
-  action::{Action, MovementMode},
+  action::{Action, MovementMode, TypingMode},
   config::{Config, KeyBindings},
   mode::Mode,
   stats::{
+    admin::{AdminCommand, FrontendCommand, ServerState, WorkerResponse},
     data::{HaproxyStat, ResourceType},
-    metrics::{HaproxyBackend, HaproxyMetrics},
+    history::StatKey,
+    master::WorkerSelection,
+    metrics::{HaproxyBackend, HaproxyFrontend, HaproxyMetrics, HaproxyServer},
   },
 };
 
+/// The name column never gets narrower than this.
+const MIN_NAME_WIDTH: u16 = 16;
+
+/// How many of the columns with `widths` fit next to the name column in a table `width` cells wide. The ones
+/// that don't are left out from the right, rather than squeezing everything together.
+fn fitting_columns(widths: &[u16], width: u16) -> usize {
+  // Every column is one cell of spacing away from the one before it
+  let mut used = MIN_NAME_WIDTH as u32;
+  widths
+    .iter()
+    .take_while(|column| {
+      used += **column as u32 + 1;
+      used <= width as u32
+    })
+    .count()
+}
+
+/// Column the table is sorted by, kept for the whole session, also across resource types. Rows stay in socket
+/// order while no column is picked, or the picked one isn't shown for the current resource type.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct ItemSort {
+  column: Option<String>,
+  descending: bool,
+}
+
+impl ItemSort {
+  /// Moves on to the column after the current one, and back to socket order after the last.
+  fn next(&mut self, headers: &[String]) {
+    let current = self.column.as_ref().and_then(|column| headers.iter().position(|header| header == column));
+    self.column = match current {
+      Some(index) => headers.get(index + 1).cloned(),
+      None => headers.first().cloned(),
+    };
+  }
+
+  /// `header` with an arrow if the table is sorted by it.
+  fn label(&self, header: &str) -> String {
+    match &self.column {
+      Some(column) if column == header => {
+        let arrow = if self.descending { "▼" } else { "▲" };
+        format!("{} {}", header, arrow).trim_start().to_string()
+      },
+      _ => header.to_string(),
+    }
+  }
+
+  /// `rows`, each an item along with what its columns show, ordered by the sort column. `name` gives the text
+  /// of the name column, which isn't one of `columns`.
+  fn sorted<'a, T>(
+    &self,
+    mut rows: Vec<(T, ColumnData<'a>)>,
+    columns: &[Column],
+    name: impl Fn(&T) -> String,
+  ) -> Vec<(T, ColumnData<'a>)> {
+    let sort_column = match self.column.as_deref() {
+      None => return rows,
+      Some("") => None,
+      Some(title) => match columns.iter().find(|column| column.title == title) {
+        Some(column) => Some(column),
+        None => return rows,
+      },
+    };
+    let key = |(item, data): &(T, ColumnData)| match sort_column {
+      Some(column) => column.sort_key(data),
+      None => SortKey::Text(name(item)),
+    };
+    rows.sort_by(|a, b| {
+      let ordering = key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal);
+      if self.descending {
+        ordering.reverse()
+      } else {
+        ordering
+      }
+    });
+    rows
+  }
+}
+
 enum LookupType {
+  Frontend(HaproxyFrontend),
   Backend(HaproxyBackend),
-  Server(HaproxyBackend),
+  /// A server along with the backend it belongs to.
+  Server(HaproxyBackend, Box<HaproxyServer>),
 }
 
 pub struct Items<'a> {
@@ -28,15 +120,36 @@ pub struct Items<'a> {
   config: Config,
   state: TableState,
   metrics: Option<Arc<HaproxyMetrics>>,
+  /// `stat_fields` of every row of `metrics`, converted once per refresh.
+  stat_fields: HashMap<StatKey, JsonValue>,
   headers: Vec<String>,
   rows: Vec<Row<'a>>,
   row_lookup: HashMap<Row<'a>, LookupType>,
   resource: ResourceType,
   status_filter: StatusType,
-  filter: Option<String>,
+  filter: Query,
+  sort: ItemSort,
+  /// Columns per resource type, starting out as configured and switched on and off with the column picker.
+  columns: HashMap<ResourceType, Vec<Column>>,
+  /// Highlighted entry of the column picker while it's open.
+  picker: Option<usize>,
+  /// Input for, confirmation of, or response to a command sent to HAProxy.
+  dialog: Option<Dialog>,
+  /// Last seen `addr` of every server, to notice when one moves.
+  addresses: HashMap<StatKey, String>,
+  /// Old and new address of servers that moved while h8r was watching.
+  moved: HashMap<StatKey, (String, String)>,
   sticky_backends: HashSet<String>,
   table: Table<'a>,
   area: Option<Rect>,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+impl Default for Items<'_> {
+  fn default() -> Self {
+    Self::new()
+  }
 }
 
 impl Items<'_> {
@@ -47,14 +160,23 @@ impl Items<'_> {
       state: TableState::default(),
       headers: Vec::default(),
       metrics: None,
+      stat_fields: HashMap::new(),
       rows: Vec::default(),
       row_lookup: HashMap::new(),
       resource: ResourceType::default(),
       status_filter: StatusType::default(),
-      filter: None,
+      filter: Query::default(),
+      sort: ItemSort::default(),
+      columns: HashMap::new(),
+      picker: None,
+      dialog: None,
+      addresses: HashMap::new(),
+      moved: HashMap::new(),
       table: Table::default(),
       sticky_backends: HashSet::new(),
       area: None,
+      endpoint: None,
+      stale: false,
     }
   }
 
@@ -81,6 +203,14 @@ impl Items<'_> {
       }
     }
 
+    fn format_server_name(name: String, slow: bool) -> Span<'static> {
+      if slow {
+        format!("{} (slow)", name).red().bold()
+      } else {
+        name.into()
+      }
+    }
+
     fn parse_backend_status(status: &str) -> StatusType {
       match status {
         "UP" => StatusType::Healthy,
@@ -89,118 +219,180 @@ impl Items<'_> {
       }
     }
 
+    fn parse_server_status(status: &str) -> StatusType {
+      match status {
+        status if status.starts_with("UP") => StatusType::Healthy,
+        "no check" => StatusType::Healthy,
+        _ => StatusType::Failing,
+      }
+    }
+
     fn parse_frontend_status(status: &HaproxyFrontendStatus) -> StatusType {
       match status {
         HaproxyFrontendStatus::Open => StatusType::Healthy,
-        HaproxyFrontendStatus::Closed => StatusType::Failing,
+        HaproxyFrontendStatus::Closed
+        | HaproxyFrontendStatus::Full
+        | HaproxyFrontendStatus::Paused
+        | HaproxyFrontendStatus::Stop => StatusType::Failing,
       }
     }
 
-    match (self.resource, &data.instant) {
-      (ResourceType::Frontend, Some(instant)) => {
-        self.headers = vec!["".to_string(), "State".to_string(), "Requests".to_string()];
-        for frontend in &instant.data.frontends {
-          let name = &frontend.name.clone().unwrap_or("".to_string());
+    // Latency bars share one scale, so they can be compared across the whole table
+    let latency_scale = data.instant.as_ref().map_or(0, |instant| {
+      let backends = instant.data.backends.iter().map(|backend| &backend.latency);
+      let servers = instant.data.servers.iter().map(|server| &server.latency);
+      backends.chain(servers).filter_map(|latency| latency.ttime).max().unwrap_or_default()
+    });
 
-          if let Some(filter) = &self.filter {
-            if !name.contains(filter) {
-              continue;
-            }
-          }
+    let columns = self.visible_columns();
+    self.headers = std::iter::once(String::new()).chain(columns.iter().map(|column| column.title.clone())).collect();
 
-          if parse_frontend_status(&frontend.status) != self.status_filter {
+    let instant = match &data.instant {
+      Some(instant) => instant,
+      None => {
+        self.headers = vec!["".to_string()];
+        rows.push(Row::new(vec!["No data available".to_string().red()]));
+        self.row_lookup = row_lookup;
+        self.rows = rows;
+        self.create_table();
+        return;
+      },
+    };
+
+    let stats: HashMap<StatKey, &HaproxyStat> =
+      instant.data.raw.iter().map(|stat| (StatKey::of(stat), stat)).collect();
+    let missing = HaproxyStat::default();
+    let column_data = |key: StatKey| {
+      let stat = stats.get(&key).copied().unwrap_or(&missing);
+      ColumnData::new(stat, self.stat_fields.get(&key).unwrap_or(&JsonValue::Null), data.rates(key), latency_scale)
+    };
+    let moved = |column: &Column, column_data: &ColumnData| {
+      let (old, new) = self.moved.get(&StatKey::of(column_data.stat))?;
+      (column.field == "addr" && column_data.stat.addr.as_ref() == Some(new)).then(|| format!("{} → {}", old, new))
+    };
+    let cells = |name: Span<'static>, column_data: &ColumnData| {
+      let cells = columns.iter().map(|column| match moved(column, column_data) {
+        Some(moved) => Cell::from(moved.yellow()),
+        None => column.cell(column_data),
+      });
+      std::iter::once(Cell::from(name)).chain(cells).collect::<Vec<Cell>>()
+    };
+
+    match self.resource {
+      ResourceType::Frontend => {
+        let mut frontends = Vec::new();
+        for frontend in &instant.data.frontends {
+          if self.status_filter != StatusType::All && parse_frontend_status(&frontend.status) != self.status_filter {
             continue;
           }
 
-          let row = Row::new(vec![name.clone(), frontend.status.to_string(), frontend.requests.to_string()]);
+          let data = column_data(frontend.key());
+          if self.filter.matches(&data) {
+            frontends.push((frontend, data));
+          }
+        }
+
+        let frontends = self.sort.sorted(frontends, &columns, |frontend| frontend.name.clone().unwrap_or_default());
+        for (frontend, column_data) in frontends {
+          let row = Row::new(cells(frontend.name.clone().unwrap_or_default().into(), &column_data));
+          row_lookup.insert(row.clone(), LookupType::Frontend(frontend.clone()));
           rows.push(row);
         }
       },
-      (ResourceType::Backend, Some(instant)) => {
-        self.headers = vec!["".to_string(), "State".to_string(), "Requests".to_string()];
-        for backend in instant.data.backends.clone() {
-          let name = backend.clone().name.unwrap_or("".to_string());
+      ResourceType::Backend => {
+        let mut backends = Vec::new();
+        for backend in &instant.data.backends {
+          let name = backend.name.clone().unwrap_or("".to_string());
 
           let is_stickied = self.sticky_backends.contains(&name);
 
-          if let Some(filter) = &self.filter {
-            if !name.contains(filter) && !is_stickied {
-              continue;
-            }
-          }
-
           if self.status_filter != StatusType::All && parse_backend_status(&backend.status) != self.status_filter {
             continue;
           }
 
-          let row = Row::new(vec![
-            format_backend_name(name, is_stickied),
-            backend.status.to_string().white(),
-            backend.requests.to_string().white(),
-          ]);
-          row_lookup.insert(row.clone(), LookupType::Backend(backend));
+          let data = column_data(backend.key());
+          if is_stickied || self.filter.matches(&data) {
+            backends.push((backend, data));
+          }
+        }
+
+        let backends = self.sort.sorted(backends, &columns, |backend| backend.name.clone().unwrap_or_default());
+        for (backend, column_data) in backends {
+          let name = backend.name.clone().unwrap_or_default();
+          let is_stickied = self.sticky_backends.contains(&name);
+          let row = Row::new(cells(format_backend_name(name, is_stickied), &column_data)).white();
+          row_lookup.insert(row.clone(), LookupType::Backend(backend.clone()));
           rows.push(row);
         }
       },
-      (ResourceType::Server, Some(instant)) => {
-        self.headers = vec!["".to_string(), "Backend".to_string(), "State".to_string(), "Requests".to_string()];
-        for server in instant.data.servers.clone() {
-          let name = server.name.unwrap_or("".to_string());
-
-          if let Some(filter) = &self.filter {
-            if !name.contains(filter) {
-              continue;
-            }
+      ResourceType::Server => {
+        let mut servers = Vec::new();
+        for server in &instant.data.servers {
+          if self.status_filter != StatusType::All && parse_server_status(&server.status) != self.status_filter {
+            continue;
           }
 
-          let row = Row::new(vec![name, server.status.to_string(), server.requests.to_string()]);
+          let data = column_data(server.key());
+          if self.filter.matches(&data) {
+            servers.push((server, data));
+          }
+        }
+
+        let servers = self.sort.sorted(servers, &columns, |server| server.name.clone().unwrap_or_default());
+        for (server, column_data) in servers {
+          let backend = instant.data.backends.iter().find(|backend| backend.name == server.backend_name);
+          let slow = backend.is_some_and(|backend| server.latency.is_outlier(&backend.latency));
+
+          let row = Row::new(cells(format_server_name(server.name.clone().unwrap_or_default(), slow), &column_data));
+          if let Some(backend) = backend {
+            row_lookup.insert(row.clone(), LookupType::Server(backend.clone(), Box::new(server.clone())));
+          }
           rows.push(row);
         }
       },
-      (ResourceType::Combined, Some(instant)) => {
-        self.headers =
-          vec!["".to_string(), "Type".to_string(), "State".to_string(), "Code".to_string(), "Requests".to_string()];
-        for backend in instant.data.backends.clone() {
-          let backend_name = backend.clone().name.unwrap_or("".to_string());
+      ResourceType::Combined => {
+        let mut backends = Vec::new();
+        for backend in &instant.data.backends {
+          let backend_name = backend.name.clone().unwrap_or("".to_string());
 
           let is_stickied = self.sticky_backends.contains(&backend_name);
 
-          if let Some(filter) = &self.filter {
-            if !backend_name.contains(filter) && !is_stickied {
-              continue;
-            }
-          }
-
           if self.status_filter != StatusType::All && parse_backend_status(&backend.status) != self.status_filter {
             continue;
           }
 
-          let backend_row = Row::new(vec![
-            format_backend_name(backend_name, is_stickied),
-            "Backend".to_string().bold(),
-            backend.status.to_string().bold(),
-            "".to_string().bold(),
-            backend.requests.to_string().bold(),
-          ]);
+          // A backend that matches brings all of its servers along, otherwise only the servers that match do
+          let data = column_data(backend.key());
+          let servers: Vec<(&HaproxyServer, ColumnData)> = backend
+            .servers
+            .iter()
+            .map(|server| (server, column_data(server.key())))
+            .filter(|(_, server_data)| is_stickied || self.filter.matches(&data) || self.filter.matches(server_data))
+            .collect();
+          if is_stickied || self.filter.matches(&data) || !servers.is_empty() {
+            backends.push(((backend, servers), data));
+          }
+        }
+
+        // Backends are sorted among themselves, and servers within their backend, so they stay grouped
+        let backends = self.sort.sorted(backends, &columns, |(backend, _)| backend.name.clone().unwrap_or_default());
+        for ((backend, servers), backend_data) in backends {
+          let backend_name = backend.name.clone().unwrap_or_default();
+          let is_stickied = self.sticky_backends.contains(&backend_name);
+          let backend_row = Row::new(cells(format_backend_name(backend_name, is_stickied), &backend_data)).bold();
           row_lookup.insert(backend_row.clone(), LookupType::Backend(backend.clone()));
           rows.push(backend_row);
-          for server in &backend.servers {
-            let server_row = Row::new(vec![
-              format!("└ {}", server.name.clone().unwrap_or("".to_string())),
-              "Server".to_string(),
-              server.status.to_string(),
-              server.status_code.to_string(),
-              server.requests.to_string(),
-            ]);
-            row_lookup.insert(server_row.clone(), LookupType::Server(backend.clone()));
+
+          let servers = self.sort.sorted(servers, &columns, |server| server.name.clone().unwrap_or_default());
+          for (server, server_data) in servers {
+            let slow = server.latency.is_outlier(&backend.latency);
+            let name = format!("└ {}", server.name.clone().unwrap_or("".to_string()));
+            let server_row = Row::new(cells(format_server_name(name, slow), &server_data));
+            row_lookup.insert(server_row.clone(), LookupType::Server(backend.clone(), Box::new(server.clone())));
             rows.push(server_row);
           }
         }
       },
-      (_, None) => {
-        self.headers = vec!["".to_string()];
-        rows.push(Row::new(vec!["No data available".to_string().red()]));
-      },
     }
 
     self.row_lookup = row_lookup;
@@ -220,10 +412,8 @@ impl Items<'_> {
     let mut next = self.find_next_row();
     while next < self.rows.len() {
       if let Some(row) = self.rows.get(next) {
-        if let Some(data) = &self.row_lookup.get(row) {
-          if let LookupType::Backend(_) = data {
-            break;
-          }
+        if let Some(LookupType::Backend(_)) = self.row_lookup.get(row) {
+          break;
         }
       }
       self.state.select(Some(next));
@@ -233,23 +423,19 @@ impl Items<'_> {
   }
 
   fn find_prev_row(&self) -> usize {
-    let selection = if self.state.selected().unwrap_or(1) == 0 {
+    if self.state.selected().unwrap_or(1) == 0 {
       self.rows.len() - 1
     } else {
       self.state.selected().unwrap_or(1) - 1
-    };
-
-    selection
+    }
   }
 
   fn find_prev_section(&mut self) -> usize {
     let mut prev = self.find_prev_row();
     while prev > 0 {
       if let Some(row) = self.rows.get(prev) {
-        if let Some(data) = &self.row_lookup.get(row) {
-          if let LookupType::Backend(_) = data {
-            break;
-          }
+        if let Some(LookupType::Backend(_)) = self.row_lookup.get(row) {
+          break;
         }
       }
       self.state.select(Some(prev));
@@ -258,28 +444,114 @@ impl Items<'_> {
     prev
   }
 
-  fn create_table(&mut self) -> () {
-    let mut lengths = Vec::new();
-    for header in &self.headers {
-      lengths.push(Constraint::Length(15));
+  /// Columns shown for the current resource type, leaving out the ones switched off.
+  fn visible_columns(&self) -> Vec<Column> {
+    let columns = self.columns.get(&self.resource).map(Vec::as_slice).unwrap_or_default();
+    columns.iter().filter(|column| !column.hidden).cloned().collect()
+  }
+
+  fn create_table(&mut self) {
+    // The name column takes whatever the others leave, columns that don't fit next to it are left out
+    let mut widths: Vec<u16> = self.visible_columns().iter().map(Column::width).collect();
+    if let Some(area) = self.area {
+      widths.truncate(fitting_columns(&widths, area.width));
+    }
+    let lengths = std::iter::once(Constraint::Min(MIN_NAME_WIDTH))
+      .chain(widths.into_iter().map(Constraint::Length))
+      .take(self.headers.len().max(1));
+
+    let table = Table::new(self.rows.iter().cloned(), lengths)
+      .header(Row::new(self.headers.iter().map(|header| self.sort.label(header))).bold())
+      .row_highlight_style(Style::new().light_yellow());
+
+    self.table = table;
+  }
+
+  fn selected(&self) -> Option<&LookupType> {
+    self.rows.get(self.state.selected()?).and_then(|row| self.row_lookup.get(row))
+  }
+
+  fn stat(&self, key: StatKey) -> Option<&HaproxyStat> {
+    let instant = self.metrics.as_ref()?.instant.as_ref()?;
+    instant.data.raw.iter().find(|stat| StatKey::of(stat) == key)
+  }
+
+  /// Backend and server name of the selected row, along with its stats, if it's a server.
+  fn selected_server(&self) -> Option<(String, String, Option<&HaproxyStat>)> {
+    match self.selected()? {
+      LookupType::Server(backend, server) => {
+        let (backend_name, server_name) = (backend.name.clone(), server.name.clone());
+        Some((backend_name.unwrap_or_default(), server_name.unwrap_or_default(), self.stat(server.key())))
+      },
+      _ => None,
     }
+  }
 
-    if lengths.is_empty() {
-      lengths.push(Constraint::Length(15));
+  /// Name of the selected row, along with its stats, if it's a frontend.
+  fn selected_frontend(&self) -> Option<(String, Option<&HaproxyStat>)> {
+    match self.selected()? {
+      LookupType::Frontend(frontend) => Some((frontend.name.clone().unwrap_or_default(), self.stat(frontend.key()))),
+      _ => None,
     }
+  }
 
-    if let Some(area) = self.area {
-      lengths[0] = Constraint::Length(area.width - (lengths.len() as u16 - 1) * 15);
+  /// Where the selected server moved from and to, shown whether or not the Address column is.
+  fn selected_move(&self) -> Option<String> {
+    let (backend, server, stat) = self.selected_server()?;
+    let (old, new) = self.moved.get(&StatKey::of(stat?))?;
+    Some(format!(" {}/{} moved from {} to {} ", backend, server, old, new))
+  }
+
+  /// Notes servers whose address differs from the last refresh, e.g. after `set server addr`.
+  fn track_addresses(&mut self, metrics: &HaproxyMetrics) {
+    let Some(instant) = &metrics.instant else { return };
+    let servers = instant.data.raw.iter().filter(|stat| stat.resource_type == Some(2));
+    let addresses: HashMap<StatKey, String> =
+      servers.filter_map(|stat| Some((StatKey::of(stat), stat.addr.clone()?))).collect();
+
+    for (key, addr) in &addresses {
+      if let Some(old) = self.addresses.get(key).filter(|old| *old != addr) {
+        log::info!("Items::track_addresses: {:?} moved from {} to {}", key, old, addr);
+        self.moved.insert(*key, (old.clone(), addr.clone()));
+      }
     }
-    // lengths[0] = Constraint::Length(area.width - (lengths.len() as u16 - 1) * 15);
+    self.addresses = addresses;
+  }
 
-    let table = Table::new(self.rows.iter().map(|row| row.clone()), lengths)
-      .header(Row::new(self.headers.clone()).bold())
-      .highlight_style(Style::new().light_yellow());
+  /// Opens `dialog`, which takes over the keyboard until it's closed.
+  fn open_dialog(&mut self, dialog: Dialog) -> Option<Action> {
+    log::debug!("Items::open_dialog: {:?}", dialog);
+    self.dialog = Some(dialog);
+    Some(Action::TypingMode(TypingMode::Dialog))
+  }
 
-    self.table = table;
+  fn toggle_column(&mut self, index: usize) {
+    if let Some(column) = self.columns.get_mut(&self.resource).and_then(|columns| columns.get_mut(index)) {
+      column.hidden = !column.hidden;
+      log::debug!("Items::toggle_column: {} hidden: {}", column.title, column.hidden);
+    }
+    self.update_rows();
   }
 
+  fn draw_column_picker(&self, f: &mut Frame<'_>, area: Rect, selected: usize) {
+    let columns = self.columns.get(&self.resource).map(Vec::as_slice).unwrap_or_default();
+    let items = columns.iter().map(|column| {
+      let check = if column.hidden { "[ ]" } else { "[x]" };
+      format!("{} {}", check, column.title)
+    });
+
+    let width = 36.min(area.width);
+    let height = (columns.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height);
+
+    let block = Block::bordered()
+      .title(" Columns ")
+      .title_bottom(" Enter toggles, Esc closes ")
+      .border_style(Style::default().fg(Color::Yellow));
+    let list = List::new(items).block(block).highlight_style(Style::new().light_yellow().reversed());
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut ListState::default().with_selected(Some(selected)));
+  }
 }
 
 impl Component for Items<'_> {
@@ -301,11 +573,38 @@ impl Component for Items<'_> {
   }
 
   fn register_config_handler(&mut self, config: Config) -> Result<()> {
+    // Switching endpoints registers the config again, which shouldn't undo what was picked for this session
+    if self.columns.is_empty() {
+      self.columns = config.columns.clone();
+    }
     self.config = config;
     Ok(())
   }
 
+  fn handle_key_events(&mut self, typing_mode: TypingMode, key: KeyEvent) -> Result<Option<Action>> {
+    if let (TypingMode::Dialog, Some(dialog)) = (typing_mode, &self.dialog) {
+      let (dialog, action) = dialog.handle_key(key);
+      if let (Some(action), Some(tx)) = (action, &self.command_tx) {
+        tx.send(action)?;
+      }
+      let closed = dialog.is_none();
+      self.dialog = dialog;
+      return Ok(closed.then_some(Action::TypingMode(TypingMode::Navigation)));
+    }
+
+    match (self.picker, key.code) {
+      (Some(_), KeyCode::Esc) => Ok(Some(Action::ToggleColumnPicker)),
+      _ => Ok(None),
+    }
+  }
+
   fn move_down(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    if let Some(selected) = self.picker {
+      let len = self.columns.get(&self.resource).map_or(0, Vec::len);
+      self.picker = Some(if selected + 1 < len { selected + 1 } else { 0 });
+      return Ok(None);
+    }
+
     if self.rows.is_empty() {
       return Ok(None);
     }
@@ -326,6 +625,12 @@ impl Component for Items<'_> {
   }
 
   fn move_up(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    if let Some(selected) = self.picker {
+      let len = self.columns.get(&self.resource).map_or(0, Vec::len);
+      self.picker = Some(if selected > 0 { selected - 1 } else { len.saturating_sub(1) });
+      return Ok(None);
+    }
+
     if self.rows.is_empty() {
       return Ok(None);
     }
@@ -348,10 +653,28 @@ impl Component for Items<'_> {
   fn update(&mut self, action: Action) -> Result<Option<Action>> {
     match action {
       Action::MetricUpdate(metrics) => {
+        self.track_addresses(&metrics);
+        let raw = metrics.instant.as_ref().map(|instant| instant.data.raw.as_slice()).unwrap_or_default();
+        self.stat_fields = raw.iter().map(|stat| (StatKey::of(stat), stat_fields(stat))).collect();
         self.metrics = Some(metrics.clone());
         self.update_rows();
         Ok(None)
       },
+      Action::ActiveEndpoint(endpoint) => {
+        // Another instance's servers have nothing to do with the ones seen so far
+        if self.endpoint.as_ref().is_some_and(|current| *current != endpoint) {
+          self.addresses.clear();
+          self.moved.clear();
+        }
+        self.endpoint = Some(endpoint);
+        Ok(None)
+      },
+      Action::CollectorState(endpoint, state) => {
+        if self.endpoint.as_ref() == Some(&endpoint) {
+          self.stale = state.is_stale();
+        }
+        Ok(None)
+      },
       Action::SelectResource(resource) => {
         self.resource = resource;
         self.state.select(Some(0));
@@ -365,11 +688,26 @@ impl Component for Items<'_> {
         Ok(None)
       },
       Action::Filter(filter_string) => {
-        self.filter = Some(filter_string);
+        // The menu shows what's wrong with a query, until then the last one that parsed stays in use
+        let extra_fields = self.metrics.as_ref().map(|metrics| metrics.extra_fields()).unwrap_or_default();
+        match Query::parse(&filter_string, &extra_fields) {
+          Ok(query) => self.filter = query,
+          Err(error) => log::debug!("Items::update: Ignoring filter {:?}: {}", filter_string, error),
+        }
         self.state.select(Some(0));
         self.update_rows();
         Ok(None)
       },
+      Action::NextSort => {
+        self.sort.next(&self.headers);
+        self.update_rows();
+        Ok(None)
+      },
+      Action::ReverseSort => {
+        self.sort.descending = !self.sort.descending;
+        self.update_rows();
+        Ok(None)
+      },
       Action::Sticky => {
         match self.state.selected() {
           Some(selection) => {
@@ -377,7 +715,9 @@ impl Component for Items<'_> {
               if let Some(data) = &self.row_lookup.get(row) {
                 let data = match data {
                   LookupType::Backend(backend) => backend,
-                  LookupType::Server(server) => server,
+                  LookupType::Server(backend, _) => backend,
+                  // Only backends can be made sticky
+                  LookupType::Frontend(_) => return Ok(None),
                 };
 
                 if self.sticky_backends.contains(&data.name.clone().unwrap_or("".to_string())) {
@@ -397,21 +737,115 @@ impl Component for Items<'_> {
 
         Ok(None)
       },
+      Action::ToggleColumnPicker => {
+        self.picker = match self.picker {
+          Some(_) => None,
+          None => Some(0),
+        };
+        Ok(None)
+      },
+      Action::SelectItem if self.picker.is_some() => {
+        self.toggle_column(self.picker.unwrap_or_default());
+        Ok(None)
+      },
+      Action::SetServerState(state) => match self.selected_server() {
+        Some((backend, server, _)) => {
+          Ok(self.open_dialog(Dialog::Confirm(AdminCommand::set_server_state(&backend, &server, state))))
+        },
+        None => {
+          log::info!("Items::update: Not a server, can't set its state to {}", state);
+          Ok(None)
+        },
+      },
+      Action::EditServerWeight => match self.selected_server() {
+        Some((backend, server, stat)) => {
+          let (weight, uweight) = stat.map_or((None, None), |stat| (stat.weight, stat.uweight));
+          Ok(self.open_dialog(Dialog::Prompt(Prompt::Weight { backend, server, weight, uweight }, String::new())))
+        },
+        None => {
+          log::info!("Items::update: Not a server, can't set its weight");
+          Ok(None)
+        },
+      },
+      Action::EditServerAddress => match self.selected_server() {
+        Some((backend, server, stat)) => {
+          let addr = stat.and_then(|stat| stat.addr.clone());
+          let input = addr.clone().unwrap_or_default();
+          Ok(self.open_dialog(Dialog::Prompt(Prompt::Address { backend, server, addr }, input)))
+        },
+        None => {
+          log::info!("Items::update: Not a server, can't change its address");
+          Ok(None)
+        },
+      },
+      Action::ResetServerWeight => match self.selected_server() {
+        Some((backend, server, _)) => {
+          Ok(self.open_dialog(Dialog::Confirm(AdminCommand::reset_server_weight(&backend, &server))))
+        },
+        None => {
+          log::info!("Items::update: Not a server, can't reset its weight");
+          Ok(None)
+        },
+      },
+      Action::ServerChecks => match self.selected_server() {
+        Some((backend, server, _)) => {
+          let commands = AdminCommand::server_checks(&backend, &server);
+          Ok(self.open_dialog(Dialog::Choose(format!("Checks of {}/{}", backend, server), commands, 0)))
+        },
+        None => {
+          log::info!("Items::update: Not a server, it has no checks");
+          Ok(None)
+        },
+      },
+      Action::SetFrontend(command) => match self.selected_frontend() {
+        Some((frontend, _)) if command == FrontendCommand::Shutdown => {
+          Ok(self.open_dialog(Dialog::Prompt(Prompt::Shutdown { frontend }, String::new())))
+        },
+        Some((frontend, _)) => Ok(self.open_dialog(Dialog::Confirm(AdminCommand::frontend(&frontend, command)))),
+        None => {
+          log::info!("Items::update: Not a frontend, can't {} it", command);
+          Ok(None)
+        },
+      },
+      Action::EditFrontendMaxconn => match self.selected_frontend() {
+        Some((frontend, stat)) => {
+          let slim = stat.and_then(|stat| stat.slim);
+          Ok(self.open_dialog(Dialog::Prompt(Prompt::Maxconn { frontend, slim }, String::new())))
+        },
+        None => {
+          log::info!("Items::update: Not a frontend, can't set its maxconn");
+          Ok(None)
+        },
+      },
+      Action::CommandResponse(endpoint, command, response) if self.endpoint.as_ref() == Some(&endpoint) => {
+        if let Some(dialog) = &mut self.dialog {
+          dialog.respond(&command, response);
+        }
+        Ok(None)
+      },
       Action::SelectItem => {
         log::info!("Selected: {:?}", self.state.selected());
         if let Some(selection) = self.state.selected() {
           if let Some(row) = self.rows.get(selection) {
             if let Some(data) = &self.row_lookup.get(row) {
               if let Some(tx) = &self.command_tx {
-                let data = match data {
-                  LookupType::Backend(backend) => backend,
-                  LookupType::Server(server) => server,
-                };
-
-                let name = data.name.clone().unwrap_or("".to_string());
-
-                tx.send(Action::SwitchMode(Mode::Info))?;
-                tx.send(Action::UseItem(name))?;
+                match data {
+                  LookupType::Frontend(frontend) => {
+                    tx.send(Action::SwitchMode(Mode::Frontend))?;
+                    tx.send(Action::UseFrontend(frontend.name.clone().unwrap_or_default()))?;
+                  },
+                  LookupType::Backend(backend) => {
+                    tx.send(Action::SwitchMode(Mode::Info))?;
+                    tx.send(Action::UseItem(backend.name.clone().unwrap_or_default()))?;
+                  },
+                  LookupType::Server(backend, server) => {
+                    tx.send(Action::SwitchMode(Mode::Server))?;
+                    tx.send(Action::UseServer(
+                      backend.name.clone().unwrap_or_default(),
+                      server.name.clone().unwrap_or_default(),
+                    ))?;
+                  },
+                }
               }
             }
           }
@@ -424,15 +858,255 @@ impl Component for Items<'_> {
   }
 
   fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
-    self.area = Some(area);
+    if self.area != Some(area) {
+      self.area = Some(area);
+      self.create_table();
+    }
 
-    let border = Block::new()
-      .title(self.resource.to_string())
+    let mut title = match &self.endpoint {
+      Some(endpoint) => format!("{} @ {}", self.resource, endpoint),
+      None => self.resource.to_string(),
+    };
+    if let Some(master) = self.metrics.as_ref().and_then(|metrics| metrics.master.as_ref()) {
+      match master.worker {
+        WorkerSelection::All => title.push_str(" [all workers]"),
+        WorkerSelection::Pid(pid) => title.push_str(&format!(" [worker {}]", pid)),
+      }
+    }
+
+    let mut border = Block::new()
+      .title(title)
       .borders(Borders::ALL)
       .border_style(Style::default().fg(Color::White));
+    if let Some(moved) = self.selected_move() {
+      border = border.title_bottom(moved.yellow());
+    }
 
     f.render_widget(border, area);
     f.render_stateful_widget(&self.table, area, &mut self.state);
+    if self.stale {
+      grey_out(f, area);
+    }
+    if let Some(selected) = self.picker {
+      let len = self.columns.get(&self.resource).map_or(0, Vec::len);
+      self.draw_column_picker(f, area, selected.min(len.saturating_sub(1)));
+    }
+    if let Some(dialog) = &self.dialog {
+      dialog.draw(f, area);
+    }
     Ok(())
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn fixture_stats() -> Vec<HaproxyStat> {
+    HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap()
+  }
+
+  fn metric_update(stats: Vec<HaproxyStat>) -> Action {
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(stats).unwrap();
+    Action::MetricUpdate(Arc::new(metrics))
+  }
+
+  fn items(resource: ResourceType) -> Items<'static> {
+    let mut items = Items::new();
+    items.register_config_handler(Config::new().unwrap()).unwrap();
+    items.update(Action::SelectResource(resource)).unwrap();
+    items.update(metric_update(fixture_stats())).unwrap();
+    items
+  }
+
+  /// The fixture table of the `local` instance, with `row` selected.
+  fn selected_items(resource: ResourceType, row: usize) -> Items<'static> {
+    let mut items = items(resource);
+    items.update(Action::ActiveEndpoint("local".to_string())).unwrap();
+    items.state.select(Some(row));
+    items
+  }
+
+  fn row_names(items: &Items) -> Vec<String> {
+    let name = |row| match items.row_lookup.get(row) {
+      Some(LookupType::Frontend(frontend)) => frontend.name.clone(),
+      Some(LookupType::Backend(backend)) => backend.name.clone(),
+      Some(LookupType::Server(_, server)) => server.name.clone(),
+      None => None,
+    };
+    items.rows.iter().map(|row| name(row).unwrap_or_default()).collect()
+  }
+
+  #[test]
+  fn test_next_sort() {
+    let headers: Vec<String> = ["", "State", "Requests"].map(String::from).to_vec();
+    let mut sort = ItemSort::default();
+    sort.next(&headers);
+    assert_eq!(sort.column.as_deref(), Some(""));
+    sort.next(&headers);
+    assert_eq!(sort.column.as_deref(), Some("State"));
+    for _ in 0..headers.len() - 1 {
+      sort.next(&headers);
+    }
+    assert_eq!(sort.column, None);
+
+    // A column the current resource type doesn't have starts over at the first one
+    sort.column = Some("Backend".to_string());
+    sort.next(&headers);
+    assert_eq!(sort.column.as_deref(), Some(""));
+  }
+
+  #[test]
+  fn test_sorted() {
+    let mut items = items(ResourceType::Server);
+    assert_eq!(row_names(&items), vec!["app1", "app2"]);
+
+    items.sort = ItemSort { column: Some("q/c/r/t ms".to_string()), descending: true };
+    items.update_rows();
+    assert_eq!(row_names(&items), vec!["app2", "app1"]);
+    assert_eq!(items.sort.label("q/c/r/t ms"), "q/c/r/t ms ▼");
+    assert_eq!(items.sort.label("State"), "State");
+
+    // Servers stay under their backend
+    items.update(Action::SelectResource(ResourceType::Combined)).unwrap();
+    assert_eq!(row_names(&items), vec!["app", "app2", "app1"]);
+
+    // Not a column of the frontend view, so it keeps the socket order
+    items.update(Action::SelectResource(ResourceType::Frontend)).unwrap();
+    assert_eq!(row_names(&items), vec!["stats", "web"]);
+    items.update(Action::NextSort).unwrap();
+    assert_eq!(row_names(&items), vec!["web", "stats"]);
+    items.update(Action::ReverseSort).unwrap();
+    assert_eq!(row_names(&items), vec!["stats", "web"]);
+  }
+
+  #[test]
+  fn test_filter() {
+    let mut items = items(ResourceType::Combined);
+    items.update(Action::Filter("status:down".to_string())).unwrap();
+    assert_eq!(row_names(&items), vec!["app", "app2"]);
+
+    // A backend that matches keeps all of its servers
+    items.update(Action::Filter("name:app".to_string())).unwrap();
+    assert_eq!(row_names(&items), vec!["app", "app1", "app2"]);
+
+    // Half-typed queries leave the last valid one in place
+    items.update(Action::Filter("rtime>".to_string())).unwrap();
+    assert_eq!(row_names(&items), vec!["app", "app1", "app2"]);
+
+    items.update(Action::Filter(String::new())).unwrap();
+    items.update(Action::SelectResource(ResourceType::Server)).unwrap();
+    items.update(Action::SelectStatus(StatusType::Failing)).unwrap();
+    assert_eq!(row_names(&items), vec!["app2"]);
+  }
+
+  #[test]
+  fn test_fitting_columns() {
+    let widths = [11, 30, 16];
+    assert_eq!(fitting_columns(&widths, 200), 3);
+    // 16 for the name and 12 for the first column, the second one would need 31 more
+    assert_eq!(fitting_columns(&widths, 28), 1);
+    assert_eq!(fitting_columns(&widths, 58), 1);
+    assert_eq!(fitting_columns(&widths, 59), 2);
+    assert_eq!(fitting_columns(&widths, 10), 0);
+  }
+
+  #[test]
+  fn test_column_picker() {
+    let mut items = items(ResourceType::Server);
+    assert_eq!(items.headers[1], "Backend");
+
+    items.update(Action::ToggleColumnPicker).unwrap();
+    items.update(Action::SelectItem).unwrap();
+    assert!(!items.headers.contains(&"Backend".to_string()));
+    assert_eq!(items.headers[1], "Address");
+
+    // Further down, hidden unless picked
+    items.move_up(MovementMode::Single).unwrap();
+    let last = items.columns[&ResourceType::Server].last().unwrap().title.clone();
+    assert!(!items.headers.contains(&last));
+    items.update(Action::SelectItem).unwrap();
+    assert_eq!(items.headers.last(), Some(&last));
+
+    // Enter only selects rows again once the picker is closed
+    items.update(Action::ToggleColumnPicker).unwrap();
+    assert_eq!(items.picker, None);
+  }
+
+  #[test]
+  fn test_set_server_state() {
+    let mut items = selected_items(ResourceType::Server, 1);
+
+    let action = items.update(Action::SetServerState(ServerState::Drain)).unwrap();
+    assert_eq!(action, Some(Action::TypingMode(TypingMode::Dialog)));
+    let command = AdminCommand::set_server_state("app", "app2", ServerState::Drain);
+    assert_eq!(items.dialog, Some(Dialog::Confirm(command.clone())));
+
+    // Answers for other instances are left alone
+    items.dialog = Some(Dialog::Waiting(command.clone()));
+    let responses = vec![WorkerResponse { pid: None, response: Ok(String::new()) }];
+    items.update(Action::CommandResponse("other".to_string(), command.command.clone(), Ok(responses.clone()))).unwrap();
+    assert_eq!(items.dialog, Some(Dialog::Waiting(command.clone())));
+    items.update(Action::CommandResponse("local".to_string(), command.command.clone(), Ok(responses.clone()))).unwrap();
+    assert_eq!(items.dialog, Some(Dialog::Response(command, Ok(responses))));
+
+    // Only servers have a state to set
+    items.dialog = None;
+    items.update(Action::SelectResource(ResourceType::Backend)).unwrap();
+    items.state.select(Some(0));
+    assert_eq!(items.update(Action::SetServerState(ServerState::Maint)).unwrap(), None);
+    assert_eq!(items.dialog, None);
+  }
+
+  #[test]
+  fn test_edit_server_weight() {
+    let mut items = selected_items(ResourceType::Server, 0);
+
+    items.update(Action::EditServerWeight).unwrap();
+    let prompt = Prompt::Weight { backend: "app".into(), server: "app1".into(), weight: Some(1), uweight: Some(1) };
+    assert_eq!(items.dialog, Some(Dialog::Prompt(prompt, String::new())));
+
+    items.update(Action::ResetServerWeight).unwrap();
+    assert_eq!(items.dialog, Some(Dialog::Confirm(AdminCommand::reset_server_weight("app", "app1"))));
+  }
+
+  #[test]
+  fn test_set_frontend() {
+    let mut items = selected_items(ResourceType::Frontend, 1);
+
+    items.update(Action::SetFrontend(FrontendCommand::Disable)).unwrap();
+    assert_eq!(items.dialog, Some(Dialog::Confirm(AdminCommand::frontend("web", FrontendCommand::Disable))));
+
+    // Shutting down takes typing the name
+    items.update(Action::SetFrontend(FrontendCommand::Shutdown)).unwrap();
+    assert_eq!(items.dialog, Some(Dialog::Prompt(Prompt::Shutdown { frontend: "web".into() }, String::new())));
+
+    items.update(Action::EditFrontendMaxconn).unwrap();
+    let prompt = Prompt::Maxconn { frontend: "web".into(), slim: Some(262120) };
+    assert_eq!(items.dialog, Some(Dialog::Prompt(prompt, String::new())));
+  }
+
+  #[test]
+  fn test_moved_server() {
+    let mut items = selected_items(ResourceType::Server, 0);
+
+    items.update(Action::EditServerAddress).unwrap();
+    let prompt = Prompt::Address { backend: "app".into(), server: "app1".into(), addr: Some("10.0.0.11:8080".into()) };
+    assert_eq!(items.dialog, Some(Dialog::Prompt(prompt, "10.0.0.11:8080".to_string())));
+
+    let mut stats = fixture_stats();
+    let app1 = stats.iter_mut().find(|stat| stat.svname.as_deref() == Some("app1")).unwrap();
+    app1.addr = Some("10.0.0.21:8080".to_string());
+    let key = StatKey::of(app1);
+    items.update(metric_update(stats)).unwrap();
+    assert_eq!(items.moved.get(&key), Some(&("10.0.0.11:8080".to_string(), "10.0.0.21:8080".to_string())));
+    assert_eq!(items.selected_move(), Some(" app/app1 moved from 10.0.0.11:8080 to 10.0.0.21:8080 ".to_string()));
+
+    // Nothing carries over to another instance
+    items.update(Action::ActiveEndpoint("other".to_string())).unwrap();
+    assert!(items.moved.is_empty());
+  }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{grey_out, Component, Frame};
+use crate::{
+  action::Action,
+  stats::{
+    latency::Latency,
+    metrics::{HaproxyBackend, HaproxyMetrics},
+  },
+};
+
+const NAME_WIDTH: u16 = 16;
+const TIMES_WIDTH: u16 = 18;
+
+/// Colours of the queue, connect, response and transfer parts of a latency bar.
+const BAR_COLORS: [Color; 4] = [Color::Yellow, Color::Magenta, Color::Cyan, Color::DarkGray];
+
+/// `queue/connect/response/total` in milliseconds, e.g. `0/1/12/15`.
+pub fn format_times(times: [Option<i64>; 4]) -> String {
+  times.map(|time| time.map(|time| time.to_string()).unwrap_or("-".to_string())).join("/")
+}
+
+pub fn averages(latency: &Latency) -> String {
+  format_times([latency.qtime, latency.ctime, latency.rtime, latency.ttime])
+}
+
+pub fn maxima(latency: &Latency) -> String {
+  format_times([latency.qtime_max, latency.ctime_max, latency.rtime_max, latency.ttime_max])
+}
+
+/// Average time spent queueing, connecting, waiting for the response and transferring, as one bar of up to
+/// `width` cells where `scale` milliseconds fill the whole bar.
+pub fn latency_bar(latency: &Latency, scale: i64, width: u16) -> Line<'static> {
+  let parts = [latency.qtime, latency.ctime, latency.rtime, latency.transfer()];
+  if scale <= 0 || parts.iter().all(Option::is_none) {
+    return Line::default();
+  }
+
+  let cells_per_ms = width as f64 / scale as f64;
+  let mut used = 0;
+  let spans = parts.iter().zip(BAR_COLORS).map(|(time, color)| {
+    let cells = ((time.unwrap_or_default() as f64 * cells_per_ms).round() as u16).min(width - used);
+    used += cells;
+    Span::styled("█".repeat(cells as usize), Style::new().fg(color))
+  });
+  Line::from(spans.collect::<Vec<Span>>())
+}
+
+/// Key to the colours of `latency_bar`.
+pub fn legend() -> Line<'static> {
+  let names = ["queue", "connect", "response", "transfer"];
+  let spans = names.iter().zip(BAR_COLORS).flat_map(|(name, color)| [" █ ".fg(color), Span::raw(*name)]);
+  Line::from(spans.collect::<Vec<Span>>())
+}
+
+/// Where the selected backend's requests spend their time, per server, with slow servers highlighted.
+#[derive(Default)]
+pub struct LatencyBreakdown {
+  metrics: Option<Arc<HaproxyMetrics>>,
+  selected_backend: Option<String>,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+impl LatencyBreakdown {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn backend(&self) -> Option<&HaproxyBackend> {
+    let instant = self.metrics.as_ref()?.instant.as_ref()?;
+    instant.data.backends.iter().find(|backend| backend.name == self.selected_backend)
+  }
+
+  fn rows(&self, bar_width: u16) -> Vec<Row<'static>> {
+    let backend = match self.backend() {
+      Some(backend) => backend,
+      None => return vec![],
+    };
+
+    let scale = std::iter::once(&backend.latency)
+      .chain(backend.servers.iter().map(|server| &server.latency))
+      .filter_map(|latency| latency.ttime)
+      .max()
+      .unwrap_or_default();
+
+    let mut rows = vec![Row::new(vec![
+      Cell::from(backend.name.clone().unwrap_or_default().bold()),
+      Cell::from(latency_bar(&backend.latency, scale, bar_width)),
+      Cell::from(averages(&backend.latency).bold()),
+      Cell::from(maxima(&backend.latency).bold()),
+    ])];
+    rows.extend(backend.servers.iter().map(|server| {
+      let name = format!("└ {}", server.name.clone().unwrap_or_default());
+      let name = match server.latency.is_outlier(&backend.latency) {
+        true => format!("{} (slow)", name).red().bold(),
+        false => name.into(),
+      };
+      Row::new(vec![
+        Cell::from(name),
+        Cell::from(latency_bar(&server.latency, scale, bar_width)),
+        Cell::from(averages(&server.latency)),
+        Cell::from(maxima(&server.latency)),
+      ])
+    }));
+    rows
+  }
+}
+
+impl Component for LatencyBreakdown {
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::MetricUpdate(metrics) => self.metrics = Some(metrics),
+      Action::UseItem(backend_name) => self.selected_backend = Some(backend_name),
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) if self.endpoint.as_ref() == Some(&endpoint) => {
+        self.stale = state.is_stale();
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let block = Block::default().borders(Borders::ALL).title(" Latency, ms ").title(legend().right_aligned());
+    // Borders and the spacing between the four columns
+    let bar_width = area.width.saturating_sub(2 + NAME_WIDTH + 2 * TIMES_WIDTH + 3);
+
+    let table = Table::new(self.rows(bar_width), [
+      Constraint::Length(NAME_WIDTH),
+      Constraint::Length(bar_width),
+      Constraint::Length(TIMES_WIDTH),
+      Constraint::Length(TIMES_WIDTH),
+    ])
+    .header(Row::new(vec!["", "", "Avg q/c/r/t", "Max q/c/r/t"]).bold())
+    .block(block);
+    f.render_widget(table, area);
+
+    if self.stale {
+      grey_out(f, area);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_latency_bar() {
+    let latency = Latency { qtime: Some(10), ctime: Some(10), rtime: Some(60), ttime: Some(100), ..Default::default() };
+    let bar = latency_bar(&latency, 200, 20);
+    let cells: Vec<usize> = bar.spans.iter().map(|span| span.content.chars().count()).collect();
+    assert_eq!(cells, vec![1, 1, 6, 2]);
+    assert_eq!(bar.spans[2].style.fg, Some(Color::Cyan));
+
+    assert_eq!(latency_bar(&Latency::default(), 200, 20), Line::default());
+    assert_eq!(latency_bar(&latency, 0, 20), Line::default());
+    // Never wider than asked for, even when the scale is off
+    assert_eq!(latency_bar(&latency, 50, 20).width(), 20);
+  }
+
+  #[test]
+  fn test_format_times() {
+    let latency = Latency { qtime: Some(0), ctime: Some(1), rtime: Some(12), ttime: Some(15), ..Default::default() };
+    assert_eq!(averages(&latency), "0/1/12/15");
+    assert_eq!(maxima(&latency), "-/-/-/-");
+  }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeSet;
+
 use color_eyre::eyre::Result;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
@@ -13,14 +15,18 @@ use crate::{
   tui::Frame,
 };
 
-use super::Component;
+use super::{query::Query, Component};
 
 #[derive(Default, Debug, Clone)]
 pub struct Menu<'a> {
   resource: ResourceType,
   status: StatusType,
   filter: TextArea<'a>,
+  /// Why the filter doesn't parse as a query, if it doesn't.
+  error: Option<String>,
   focused: bool,
+  /// Columns the current source reports on top of `HaproxyStat`'s fields, which queries may use too.
+  extra_fields: BTreeSet<String>,
 }
 
 impl Menu<'_> {
@@ -38,13 +44,17 @@ impl Component for Menu<'_> {
         self.focused = typing_mode == TypingMode::Filter;
         Ok(None)
       },
+      Action::MetricUpdate(metrics) => {
+        self.extra_fields = metrics.extra_fields();
+        Ok(None)
+      },
       _ => Ok(None),
     }
   }
 
   fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) -> Result<()> {
-    let resources = vec![ResourceType::Combined, ResourceType::Backend, ResourceType::Frontend, ResourceType::Server];
-    let status = vec![StatusType::All, StatusType::Failing, StatusType::Healthy];
+    let resources = [ResourceType::Combined, ResourceType::Backend, ResourceType::Frontend, ResourceType::Server];
+    let status = [StatusType::All, StatusType::Failing, StatusType::Healthy];
 
     let sides = Layout::default()
       .direction(Direction::Horizontal)
@@ -80,7 +90,7 @@ impl Component for Menu<'_> {
       f.render_widget(resource_widget, left_resources[i + 1]);
     }
 
-    let status_keys = vec!['a', 'e', 'h'];
+    let status_keys = ['a', 'e', 'h'];
     for i in 0..status.len() {
       let status = status[i];
       let status_inactive_style = Style::default().fg(Color::White);
@@ -92,11 +102,15 @@ impl Component for Menu<'_> {
       f.render_widget(status_widget, left_status[i + 1]);
     }
 
-    let filter_border = Block::new().title("Filter").borders(Borders::ALL);
+    let filter_border = match &self.error {
+      Some(error) => Block::new().title(format!("Filter: {}", error)).borders(Borders::ALL),
+      None => Block::new().title("Filter").borders(Borders::ALL),
+    };
 
-    let filter_border = match self.focused {
-      true => filter_border.border_style(Style::default().fg(Color::Yellow)),
-      false => filter_border.border_style(Style::default().fg(Color::White)),
+    let filter_border = match (&self.error, self.focused) {
+      (Some(_), _) => filter_border.border_style(Style::default().fg(Color::Red)),
+      (None, true) => filter_border.border_style(Style::default().fg(Color::Yellow)),
+      (None, false) => filter_border.border_style(Style::default().fg(Color::White)),
     };
 
     f.render_widget(self.filter.widget(), filter_border.inner(right[0]));
@@ -107,6 +121,10 @@ impl Component for Menu<'_> {
   }
 
   fn handle_key_events(&mut self, typing_mode: TypingMode, key: KeyEvent) -> Result<Option<Action>> {
+    if typing_mode == TypingMode::Dialog {
+      return Ok(None);
+    }
+
     if typing_mode == TypingMode::Filter {
       match key {
         KeyEvent { code: KeyCode::Esc, .. } | KeyEvent { code: KeyCode::Enter, .. } => {
@@ -115,7 +133,9 @@ impl Component for Menu<'_> {
         },
         input => {
           self.filter.input(input);
-          Ok(Some(Action::Filter(self.filter.lines()[0].to_string())))
+          let filter = self.filter.lines()[0].to_string();
+          self.error = Query::parse(&filter, &self.extra_fields).err().map(|error| error.to_string());
+          Ok(Some(Action::Filter(filter)))
         },
       }
     } else {
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{grey_out, Component, Frame};
+use crate::{
+  action::{Action, MovementMode},
+  stats::{
+    master::{HaproxyProcess, MasterInfo, ProcessKind, WorkerSelection},
+    metrics::HaproxyMetrics,
+  },
+};
+
+/// Lists the processes behind a master CLI and picks which worker's stats are shown.
+#[derive(Default)]
+pub struct Processes {
+  command_tx: Option<UnboundedSender<Action>>,
+  state: TableState,
+  master: Option<MasterInfo>,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+impl Processes {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn processes(&self) -> &[HaproxyProcess] {
+    self.master.as_ref().map(|master| master.processes.as_slice()).unwrap_or_default()
+  }
+}
+
+impl Component for Processes {
+  fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+    self.command_tx = Some(tx);
+    Ok(())
+  }
+
+  fn move_down(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    let len = self.processes().len();
+    if len > 0 {
+      self.state.select(Some(self.state.selected().map_or(0, |selected| (selected + 1) % len)));
+    }
+    Ok(None)
+  }
+
+  fn move_up(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    let len = self.processes().len();
+    if len > 0 {
+      self.state.select(Some(self.state.selected().map_or(0, |selected| (selected + len - 1) % len)));
+    }
+    Ok(None)
+  }
+
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::MetricUpdate(metrics) => {
+        self.master = metrics.master.clone();
+        if self.state.selected().is_none_or(|selected| selected >= self.processes().len()) {
+          self.state.select(if self.processes().is_empty() { None } else { Some(0) });
+        }
+      },
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) => {
+        if self.endpoint.as_ref() == Some(&endpoint) {
+          self.stale = state.is_stale();
+        }
+      },
+      Action::SelectItem => {
+        let process = self.state.selected().and_then(|selected| self.processes().get(selected));
+        match process {
+          Some(process) if process.kind == ProcessKind::Worker => {
+            return Ok(Some(Action::SelectWorker(WorkerSelection::Pid(process.pid))));
+          },
+          Some(_) => return Ok(Some(Action::SelectWorker(WorkerSelection::All))),
+          None => {},
+        }
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let master = match &self.master {
+      Some(master) => master,
+      None => {
+        let message = Paragraph::new("Not connected to a master CLI, there are no workers to choose from".dark_gray())
+          .block(Block::new().title("Processes").borders(Borders::ALL));
+        f.render_widget(message, area);
+        return Ok(());
+      },
+    };
+
+    let headers = ["", "PID", "Type", "State", "Reloads", "Uptime", "Version", "Sessions"];
+
+    let rows = master.processes.iter().map(|process| {
+      let selected = match master.worker {
+        WorkerSelection::All => process.kind == ProcessKind::Worker,
+        WorkerSelection::Pid(pid) => pid == process.pid,
+      };
+      let state = match (process.kind, process.old) {
+        (ProcessKind::Worker, _) if master.skipped.contains(&process.pid) => "no answer".red(),
+        (ProcessKind::Worker, true) => "draining".yellow(),
+        (ProcessKind::Worker, false) => "current".green(),
+        _ => "".into(),
+      };
+
+      Row::new(vec![
+        Cell::from(if selected { "*" } else { "" }),
+        Cell::from(process.pid.to_string()),
+        Cell::from(process.kind.to_string()),
+        Cell::from(state),
+        Cell::from(process.reloads.map(|reloads| reloads.to_string()).unwrap_or_default()),
+        Cell::from(process.uptime.clone()),
+        Cell::from(process.version.clone()),
+        Cell::from(process.sessions.map(|sessions| sessions.to_string()).unwrap_or_default()),
+      ])
+    });
+
+    let widths = [
+      Constraint::Length(2),
+      Constraint::Length(10),
+      Constraint::Length(10),
+      Constraint::Length(10),
+      Constraint::Length(10),
+      Constraint::Length(15),
+      Constraint::Length(20),
+      Constraint::Min(10),
+    ];
+
+    let title = match master.worker {
+      WorkerSelection::All => "Processes (showing all workers)".to_string(),
+      WorkerSelection::Pid(pid) => format!("Processes (showing worker {})", pid),
+    };
+
+    let table = Table::new(rows, widths)
+      .header(Row::new(headers).bold())
+      .row_highlight_style(Style::new().light_yellow())
+      .block(Block::new().title(title).borders(Borders::ALL).border_style(Style::default().fg(Color::White)));
+
+    f.render_stateful_widget(table, area, &mut self.state);
+    if self.stale {
+      grey_out(f, area);
+    }
+    Ok(())
+  }
+}
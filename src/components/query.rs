@@ -0,0 +1,222 @@
+use std::collections::BTreeSet;
+
+use regex::{Regex, RegexBuilder};
+use thiserror::Error;
+
+use super::columns::{is_field, ColumnData};
+
+/// Operators between a field and a value, longest first so that `>=` isn't read as `>`.
+const OPERATORS: [&str; 8] = [">=", "<=", "!=", ":", "~", ">", "<", "="];
+
+/// Short names for fields that are awkward to type.
+const ALIASES: [(&str, &str); 4] =
+  [("5xx%", "rate.ratio_5xx"), ("4xx%", "rate.ratio_4xx"), ("req/s", "rate.requests"), ("err/s", "rate.errors")];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum QueryError {
+  #[error("unknown field '{0}'")]
+  UnknownField(String),
+  #[error("missing field before '{0}'")]
+  MissingField(String),
+  #[error("missing value after '{0}'")]
+  MissingValue(String),
+  #[error("'{0}' is not a number")]
+  NotANumber(String),
+  #[error("invalid regex '{0}'")]
+  InvalidRegex(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+  Less,
+  LessOrEqual,
+  Greater,
+  GreaterOrEqual,
+  Equal,
+  NotEqual,
+}
+
+impl Comparison {
+  fn holds(self, left: f64, right: f64) -> bool {
+    match self {
+      Comparison::Less => left < right,
+      Comparison::LessOrEqual => left <= right,
+      Comparison::Greater => left > right,
+      Comparison::GreaterOrEqual => left >= right,
+      Comparison::Equal => left == right,
+      Comparison::NotEqual => left != right,
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+  /// A bare word, found anywhere in the name.
+  Name(String),
+  /// `field:value`, ignoring case.
+  Equals(String, String),
+  /// `field~regex`, ignoring case.
+  Matches(String, Regex),
+  /// `field>number` and the other comparisons.
+  Compare(String, Comparison, f64),
+}
+
+#[derive(Debug, Clone)]
+struct Term {
+  negated: bool,
+  condition: Condition,
+}
+
+impl Term {
+  fn parse(token: &str, extra_fields: &BTreeSet<String>) -> Result<Term, QueryError> {
+    let (negated, token) = match token.strip_prefix(['-', '!']) {
+      Some(rest) if !rest.is_empty() => (true, rest),
+      _ => (false, token),
+    };
+    let name = || Term { negated, condition: Condition::Name(token.to_lowercase()) };
+
+    let split = token.find(|c: char| !(c.is_alphanumeric() || "_.%/".contains(c))).unwrap_or(token.len());
+    let (field, rest) = token.split_at(split);
+    let operator = match OPERATORS.iter().find(|operator| rest.starts_with(**operator)) {
+      Some(operator) => *operator,
+      // Just a word that happens to contain e.g. a dash
+      None => return Ok(name()),
+    };
+    let value = &rest[operator.len()..];
+
+    if field.is_empty() {
+      return Err(QueryError::MissingField(operator.to_string()));
+    }
+    if value.is_empty() {
+      return Err(QueryError::MissingValue(format!("{}{}", field, operator)));
+    }
+    let field = ALIASES.iter().find(|(alias, _)| alias.eq_ignore_ascii_case(field)).map_or(field, |(_, field)| field);
+    if !is_field(field, extra_fields) {
+      return Err(QueryError::UnknownField(field.to_string()));
+    }
+    let field = field.to_string();
+    let number = || value.parse::<f64>().map_err(|_| QueryError::NotANumber(value.to_string()));
+
+    let (negated, condition) = match operator {
+      ":" => (negated, Condition::Equals(field, value.to_string())),
+      "~" => {
+        let regex = RegexBuilder::new(value).case_insensitive(true).build();
+        (negated, Condition::Matches(field, regex.map_err(|_| QueryError::InvalidRegex(value.to_string()))?))
+      },
+      // Text fields can be compared for (in)equality too
+      "=" | "!=" if number().is_err() => (negated != (operator == "!="), Condition::Equals(field, value.to_string())),
+      _ => {
+        let comparison = match operator {
+          ">=" => Comparison::GreaterOrEqual,
+          "<=" => Comparison::LessOrEqual,
+          "!=" => Comparison::NotEqual,
+          ">" => Comparison::Greater,
+          "<" => Comparison::Less,
+          _ => Comparison::Equal,
+        };
+        (negated, Condition::Compare(field, comparison, number()?))
+      },
+    };
+    Ok(Term { negated, condition })
+  }
+
+  fn matches(&self, data: &ColumnData) -> bool {
+    let text = |field: &str| data.text(field).or_else(|| data.number(field).map(|number| number.to_string()));
+    let matches = match &self.condition {
+      Condition::Name(word) => data.name().to_lowercase().contains(word),
+      Condition::Equals(field, value) => text(field).is_some_and(|text| text.eq_ignore_ascii_case(value)),
+      Condition::Matches(field, regex) => text(field).is_some_and(|text| regex.is_match(&text)),
+      Condition::Compare(field, comparison, value) => {
+        data.number(field).is_some_and(|number| comparison.holds(number, *value))
+      },
+    };
+    matches != self.negated
+  }
+}
+
+/// A filter such as `status:DOWN mode:http name~^api- scur>100 5xx%>1`. Every term has to match. A term is
+/// either a bare word that the name has to contain, or a field, an operator and a value:
+///
+/// - `field:value` compares text, ignoring case
+/// - `field~regex` matches a regular expression, ignoring case
+/// - `field>n`, `>=`, `<`, `<=`, `=` and `!=` compare numbers
+///
+/// A leading `-` or `!` negates a term.
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+  terms: Vec<Term>,
+}
+
+impl Query {
+  /// `extra_fields` are the columns the source reports that `HaproxyStat` has no field for.
+  pub fn parse(input: &str, extra_fields: &BTreeSet<String>) -> Result<Query, QueryError> {
+    let terms = input.split_whitespace().map(|token| Term::parse(token, extra_fields));
+    let terms = terms.collect::<Result<Vec<Term>, QueryError>>()?;
+    Ok(Query { terms })
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.terms.is_empty()
+  }
+
+  pub fn matches(&self, data: &ColumnData) -> bool {
+    self.terms.iter().all(|term| term.matches(data))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+  use serde_json::Value as JsonValue;
+
+  use super::*;
+  use crate::{
+    components::columns::stat_fields,
+    stats::{data::HaproxyStat, rates::Rates},
+  };
+
+  fn matching(query: &str) -> Vec<String> {
+    let query = Query::parse(query, &BTreeSet::new()).unwrap();
+    let stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let fields: Vec<JsonValue> = stats.iter().map(stat_fields).collect();
+    stats
+      .iter()
+      .zip(&fields)
+      .map(|(stat, fields)| {
+        let rates = match stat.svname.as_deref() {
+          Some("app2") => Rates { http_5xx: Some(3.0), http_responses: Some(10.0), ..Default::default() },
+          _ => Rates::default(),
+        };
+        ColumnData::new(stat, fields, rates, 0)
+      })
+      .filter(|data| query.matches(data))
+      .map(|data| data.name().to_string())
+      .collect()
+  }
+
+  #[test]
+  fn test_matches() {
+    assert_eq!(matching(""), vec!["stats", "web", "app1", "app2", "app"]);
+    assert_eq!(matching("APP"), vec!["app1", "app2", "app"]);
+    assert_eq!(matching("status:down"), vec!["app2"]);
+    assert_eq!(matching("-status:down type:server"), vec!["app1"]);
+    assert_eq!(matching("name~^app\\d$"), vec!["app1", "app2"]);
+    assert_eq!(matching("rtime>15"), vec!["app2"]);
+    assert_eq!(matching("rtime>=12 rtime<=15"), vec!["app1", "app"]);
+    assert_eq!(matching("sid=2"), vec!["app2"]);
+    assert_eq!(matching("5xx%>1"), vec!["app2"]);
+    assert_eq!(matching("type!=server app"), vec!["app"]);
+  }
+
+  #[test]
+  fn test_errors() {
+    let error = |query: &str| Query::parse(query, &BTreeSet::new()).unwrap_err();
+    assert_eq!(error("scru>1"), QueryError::UnknownField("scru".to_string()));
+    assert_eq!(error(":DOWN"), QueryError::MissingField(":".to_string()));
+    assert_eq!(error("status:"), QueryError::MissingValue("status:".to_string()));
+    assert_eq!(error("scur>lots"), QueryError::NotANumber("lots".to_string()));
+    assert_eq!(error("name~(api"), QueryError::InvalidRegex("(api".to_string()));
+    assert!(Query::parse("  ", &BTreeSet::new()).unwrap().is_empty());
+    assert!(Query::parse("h3_open>1", &BTreeSet::from(["h3_open".to_string()])).is_ok());
+  }
+}
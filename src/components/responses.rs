@@ -0,0 +1,242 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+use strum::Display;
+
+use super::{columns::kind, details::value, grey_out, Component, Frame};
+use crate::{
+  action::{Action, MovementMode},
+  stats::{
+    data::HaproxyStat,
+    history::StatKey,
+    metrics::HaproxyMetrics,
+    rates::{format_percent, Rates},
+  },
+};
+
+const HEADERS: [&str; 10] = ["", "Type", "1xx", "2xx", "3xx", "4xx", "5xx", "Other", "4xx%", "5xx%"];
+
+/// Order of the rows, switched between with `Action::NextSort`.
+#[derive(Default, Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseSort {
+  /// As listed by HAProxy.
+  #[default]
+  #[strum(serialize = "config order")]
+  Config,
+  #[strum(serialize = "5xx%")]
+  Ratio5xx,
+  #[strum(serialize = "4xx%")]
+  Ratio4xx,
+}
+
+impl ResponseSort {
+  fn next(self) -> Self {
+    match self {
+      ResponseSort::Config => ResponseSort::Ratio5xx,
+      ResponseSort::Ratio5xx => ResponseSort::Ratio4xx,
+      ResponseSort::Ratio4xx => ResponseSort::Config,
+    }
+  }
+
+  fn ratio(self, rates: &Rates) -> Option<f64> {
+    match self {
+      ResponseSort::Config => None,
+      ResponseSort::Ratio5xx => rates.ratio_5xx(),
+      ResponseSort::Ratio4xx => rates.ratio_4xx(),
+    }
+  }
+}
+
+/// Response code counts of every frontend, backend and server, with error ratios since the previous refresh.
+#[derive(Default)]
+pub struct Responses {
+  metrics: Option<Arc<HaproxyMetrics>>,
+  state: TableState,
+  sort: ResponseSort,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+fn name(stat: &HaproxyStat) -> String {
+  match stat.resource_type {
+    Some(2) => format!("{}/{}", value(&stat.pxname), value(&stat.svname)),
+    _ => value(&stat.pxname),
+  }
+}
+
+/// Stats with their rates, highest ratio first for the error sorts. Rows without a ratio, e.g. TCP proxies or
+/// ones without traffic since the last refresh, go last.
+fn sorted_rows(metrics: &HaproxyMetrics, sort: ResponseSort) -> Vec<(&HaproxyStat, Rates)> {
+  let stats = metrics.instant.as_ref().map(|instant| instant.data.raw.as_slice()).unwrap_or_default();
+  let mut rows: Vec<(&HaproxyStat, Rates)> = stats
+    .iter()
+    .filter(|stat| stat.resource_type.is_some_and(|kind| kind <= 2))
+    .map(|stat| (stat, metrics.rates(StatKey::of(stat))))
+    .collect();
+
+  if sort != ResponseSort::Config {
+    // Stable, so equal ratios keep HAProxy's order
+    rows.sort_by(|(_, a), (_, b)| match (sort.ratio(a), sort.ratio(b)) {
+      (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+      (Some(_), None) => Ordering::Less,
+      (None, Some(_)) => Ordering::Greater,
+      (None, None) => Ordering::Equal,
+    });
+  }
+  rows
+}
+
+fn ratio_cell(ratio: Option<f64>) -> Cell<'static> {
+  let text = format_percent(ratio);
+  match ratio {
+    Some(ratio) if ratio >= 5.0 => Cell::from(text.red().bold()),
+    Some(ratio) if ratio >= 1.0 => Cell::from(text.yellow()),
+    _ => Cell::from(text),
+  }
+}
+
+impl Responses {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn len(&self) -> usize {
+    self.metrics.as_ref().map_or(0, |metrics| sorted_rows(metrics, self.sort).len())
+  }
+}
+
+impl Component for Responses {
+  fn move_down(&mut self, _mode: MovementMode) -> Result<Option<Action>> {
+    let len = self.len();
+    if len > 0 {
+      self.state.select(Some(self.state.selected().map_or(0, |selected| (selected + 1) % len)));
+    }
+    Ok(None)
+  }
+
+  fn move_up(&mut self, _mode: MovementMode) -> Result<Option<Action>> {
+    let len = self.len();
+    if len > 0 {
+      self.state.select(Some(self.state.selected().map_or(0, |selected| (selected + len - 1) % len)));
+    }
+    Ok(None)
+  }
+
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::MetricUpdate(metrics) => self.metrics = Some(metrics),
+      Action::NextSort => {
+        self.sort = self.sort.next();
+        self.state.select(Some(0));
+      },
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) if self.endpoint.as_ref() == Some(&endpoint) => {
+        self.stale = state.is_stale();
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let title = match &self.endpoint {
+      Some(endpoint) => format!("Responses @ {}, sorted by {}", endpoint, self.sort),
+      None => format!("Responses, sorted by {}", self.sort),
+    };
+    let block = Block::new().title(title).borders(Borders::ALL);
+
+    let metrics = match &self.metrics {
+      Some(metrics) => metrics,
+      None => {
+        f.render_widget(Paragraph::new("No data available".red()).block(block), area);
+        return Ok(());
+      },
+    };
+
+    let rows = sorted_rows(metrics, self.sort).into_iter().map(|(stat, rates)| {
+      Row::new(vec![
+        Cell::from(name(stat)),
+        Cell::from(kind(stat)),
+        Cell::from(value(&stat.hrsp_1xx)),
+        Cell::from(value(&stat.hrsp_2xx)),
+        Cell::from(value(&stat.hrsp_3xx)),
+        Cell::from(value(&stat.hrsp_4xx)),
+        Cell::from(value(&stat.hrsp_5xx)),
+        Cell::from(value(&stat.hrsp_other)),
+        ratio_cell(rates.ratio_4xx()),
+        ratio_cell(rates.ratio_5xx()),
+      ])
+    });
+
+    let mut widths = vec![Constraint::Length(10); HEADERS.len()];
+    widths[0] = Constraint::Min(20);
+    let table = Table::new(rows, widths)
+      .header(Row::new(HEADERS.to_vec()).bold())
+      .row_highlight_style(Style::new().light_yellow())
+      .block(block);
+    f.render_stateful_widget(table, area, &mut self.state);
+
+    if self.stale {
+      grey_out(f, area);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Local;
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::stats::history::History;
+
+  fn metrics() -> HaproxyMetrics {
+    let start = Local::now();
+    let mut stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let mut history = History::default();
+    history.record(start, &stats);
+    // The web frontend answers 10% 5xx and the app backend 20% 4xx, nothing else has traffic
+    for stat in stats.iter_mut() {
+      let (ok, client, server) = match (stat.pxname.as_deref(), stat.resource_type) {
+        (Some("web"), _) => (90.0, 0.0, 10.0),
+        (Some("app"), Some(1)) => (80.0, 20.0, 0.0),
+        _ => (0.0, 0.0, 0.0),
+      };
+      stat.hrsp_2xx = stat.hrsp_2xx.map(|count| count + ok);
+      stat.hrsp_4xx = stat.hrsp_4xx.map(|count| count + client);
+      stat.hrsp_5xx = stat.hrsp_5xx.map(|count| count + server);
+    }
+    history.record(start + chrono::Duration::seconds(10), &stats);
+
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(stats).unwrap();
+    metrics.set_history(history);
+    metrics
+  }
+
+  #[test]
+  fn test_sorted_rows() {
+    let metrics = metrics();
+    let names = |sort| sorted_rows(&metrics, sort).iter().map(|(stat, _)| name(stat)).collect::<Vec<String>>();
+
+    assert_eq!(names(ResponseSort::Config), vec!["stats", "web", "app/app1", "app/app2", "app"]);
+    assert_eq!(names(ResponseSort::Ratio5xx)[..2], ["web", "app"]);
+    assert_eq!(names(ResponseSort::Ratio4xx)[..2], ["app", "web"]);
+
+    let rows = sorted_rows(&metrics, ResponseSort::Ratio5xx);
+    assert_eq!(rows[0].1.ratio_5xx(), Some(10.0));
+  }
+
+  #[test]
+  fn test_next_sort() {
+    let mut responses = Responses::new();
+    responses.update(Action::NextSort).unwrap();
+    assert_eq!(responses.sort, ResponseSort::Ratio5xx);
+    responses.update(Action::NextSort).unwrap();
+    responses.update(Action::NextSort).unwrap();
+    assert_eq!(responses.sort, ResponseSort::Config);
+  }
+}
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{
+  details::{draw_sections, millis, of_limit, seconds, value, Section},
+  grey_out, Component, Frame,
+};
+use crate::{
+  action::Action,
+  stats::{data::HaproxyStat, metrics::HaproxyMetrics},
+};
+
+/// Every field of one server's stats that's worth looking at while debugging it, grouped by topic.
+#[derive(Default)]
+pub struct ServerDetails {
+  metrics: Option<Arc<HaproxyMetrics>>,
+  backend: Option<String>,
+  server: Option<String>,
+  endpoint: Option<String>,
+  stale: bool,
+}
+
+fn status(stat: &HaproxyStat) -> Section {
+  Section::new("Server", vec![
+    ("Address", value(&stat.addr)),
+    ("Status", value(&stat.status)),
+    ("Weight", format!("{} (configured {})", value(&stat.weight), value(&stat.uweight))),
+    ("Role", if stat.bck == Some(1) { "backup".to_string() } else { "active".to_string() }),
+    ("Last change", seconds(&stat.lastchg)),
+    ("Downtime", seconds(&stat.downtime)),
+    ("Went down", format!("{} times", value(&stat.chkdown))),
+    ("Last session", seconds(&stat.lastsess)),
+    ("Sessions", of_limit(&stat.scur, &stat.slim)),
+    ("Queue", of_limit(&stat.qcur, &stat.qlimit)),
+  ])
+}
+
+fn checks(stat: &HaproxyStat) -> Section {
+  let (health, rise, fall) = (value(&stat.check_health), value(&stat.check_rise), value(&stat.check_fall));
+  let health = format!("{} (rise {}, fall {})", health, rise, fall);
+  Section::new("Checks", vec![
+    ("Check", format!("{} {}", value(&stat.check_status), stat.check_code.clone().unwrap_or_default())),
+    ("Duration", millis(&stat.check_duration)),
+    ("Result", value(&stat.check_desc)),
+    ("Last output", value(&stat.last_chk)),
+    ("Health", health),
+    ("Failed", value(&stat.chkfail)),
+    ("Agent", format!("{} {}", value(&stat.agent_status), stat.agent_code.clone().unwrap_or_default())),
+    ("Agent time", millis(&stat.agent_duration)),
+    ("Agent result", value(&stat.agent_desc)),
+    ("Agent output", value(&stat.last_agt)),
+  ])
+}
+
+fn timings(stat: &HaproxyStat) -> Section {
+  let timing = |average: &Option<i64>, max: &Option<i64>| format!("{} (max {})", millis(average), millis(max));
+  Section::new("Timings", vec![
+    ("Queue", timing(&stat.qtime, &stat.qtime_max)),
+    ("Connect", timing(&stat.ctime, &stat.ctime_max)),
+    ("Response", timing(&stat.rtime, &stat.rtime_max)),
+    ("Total", timing(&stat.ttime, &stat.ttime_max)),
+  ])
+}
+
+fn connections(stat: &HaproxyStat) -> Section {
+  Section::new("Connections", vec![
+    ("Established", value(&stat.connect)),
+    ("Reused", value(&stat.reuse)),
+    ("Idle", of_limit(&stat.srv_icur, &stat.src_ilim)),
+    ("Idle safe", value(&stat.safe_conn_cur)),
+    ("In use", value(&stat.used_conn_cur)),
+    ("Needed", value(&stat.need_conn_est)),
+  ])
+}
+
+fn errors(stat: &HaproxyStat) -> Section {
+  Section::new("Errors", vec![
+    ("Connection", value(&stat.econ)),
+    ("Response", value(&stat.eresp)),
+    ("Retries", value(&stat.wretr)),
+    ("Redispatches", value(&stat.wredis)),
+    ("Client aborts", value(&stat.cli_abrt)),
+    ("Server aborts", value(&stat.srv_abrt)),
+    ("Internal", value(&stat.eint)),
+  ])
+}
+
+/// Sections shown for a server, in display order.
+fn sections(stat: &HaproxyStat) -> Vec<Section> {
+  vec![status(stat), checks(stat), timings(stat), connections(stat), errors(stat)]
+}
+
+impl ServerDetails {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn stat(&self) -> Option<&HaproxyStat> {
+    let instant = self.metrics.as_ref()?.instant.as_ref()?;
+    instant.data.raw.iter().find(|stat| {
+      stat.resource_type == Some(2) && stat.pxname == self.backend && stat.svname == self.server
+    })
+  }
+}
+
+impl Component for ServerDetails {
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::MetricUpdate(metrics) => self.metrics = Some(metrics),
+      Action::UseServer(backend, server) => {
+        log::info!("ServerDetails::update: Switching to server {}/{}", backend, server);
+        self.backend = Some(backend);
+        self.server = Some(server);
+      },
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) if self.endpoint.as_ref() == Some(&endpoint) => {
+        self.stale = state.is_stale();
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let title = format!(" {}/{} ", self.backend.clone().unwrap_or_default(), self.server.clone().unwrap_or_default());
+    let border = Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow)).title(title);
+    let inner = border.inner(area);
+    f.render_widget(border, area);
+
+    match self.stat() {
+      Some(stat) => draw_sections(f, inner, &sections(stat), 3),
+      None => f.render_widget(Paragraph::new("No data for this server yet"), inner),
+    }
+
+    if self.stale {
+      grey_out(f, area);
+    }
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_sections() {
+    let stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let app1 = stats.iter().find(|stat| stat.svname.as_deref() == Some("app1")).unwrap();
+
+    let sections = sections(app1);
+    let titles: Vec<&str> = sections.iter().map(|section| section.title).collect();
+    assert_eq!(titles, vec!["Server", "Checks", "Timings", "Connections", "Errors"]);
+    assert_eq!(sections[0].rows[0], ("Address", app1.addr.clone().unwrap_or("-".to_string())));
+    assert_eq!(sections[0].rows[3], ("Role", "active".to_string()));
+  }
+
+  #[test]
+  fn test_finds_selected_server() {
+    let stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(stats).unwrap();
+
+    let mut details = ServerDetails::new();
+    details.update(Action::MetricUpdate(Arc::new(metrics))).unwrap();
+    assert_eq!(details.stat(), None);
+
+    details.update(Action::UseServer("app".to_string(), "app2".to_string())).unwrap();
+    assert_eq!(details.stat().and_then(|stat| stat.sid), Some(2));
+  }
+}
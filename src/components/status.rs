@@ -15,17 +15,23 @@ use tui_textarea::TextArea;
 
 use crate::{
   action::{Action, TypingMode},
-  stats::{data::ResourceType, metrics::HaproxyMetrics},
+  stats::{
+    data::ResourceType,
+    metrics::HaproxyMetrics,
+    rates::{format_bytes_rate, format_rate},
+  },
   tui::Frame,
 };
 
-use super::Component;
+use super::{grey_out, Component};
 
 #[derive(Debug, Clone)]
 pub struct Status {
   metrics: Option<Arc<HaproxyMetrics>>,
   selected_backend: Option<String>,
   cached_metrics: Vec<Option<String>>,
+  endpoint: Option<String>,
+  stale: bool,
 }
 
 impl Default for Status {
@@ -40,7 +46,9 @@ impl Status {
     Self { 
       metrics: None, 
       selected_backend: None, 
-      cached_metrics: vec![None; STATUS_METRICS.len()]
+      cached_metrics: vec![None; STATUS_METRICS.len()],
+      endpoint: None,
+      stale: false,
     }
   }
 
@@ -70,17 +78,35 @@ enum StatusMetrics {
   ActiveServers,
   #[strum(serialize = "Backup Servers")]
   BackupServers,
+  #[strum(serialize = "Requests")]
+  RequestRate,
+  #[strum(serialize = "Errors")]
+  ErrorRate,
+  #[strum(serialize = "5xx")]
+  Http5xxRate,
+  #[strum(serialize = "In")]
+  BytesInRate,
+  #[strum(serialize = "Out")]
+  BytesOutRate,
 }
 
-const STATUS_METRICS: [StatusMetrics; 6] = [
+const STATUS_METRICS: [StatusMetrics; 11] = [
   StatusMetrics::BackendName,
   StatusMetrics::BackendStatus,
   StatusMetrics::ProxyMode,
   StatusMetrics::CurrentSessions,
   StatusMetrics::ActiveServers,
   StatusMetrics::BackupServers,
+  StatusMetrics::RequestRate,
+  StatusMetrics::ErrorRate,
+  StatusMetrics::Http5xxRate,
+  StatusMetrics::BytesInRate,
+  StatusMetrics::BytesOutRate,
 ];
 
+/// Rows of metrics per column in the panel.
+const STATUS_ROWS: usize = 3;
+
 impl Status {
   fn get_metric(&self, metric: StatusMetrics) -> Option<String> {
     log::trace!("Status::get_metric: Retrieving metric: {:?} for backend: {:?}", metric, self.selected_backend);
@@ -107,6 +133,7 @@ impl Status {
     }
     let backend = backend?;
 
+    let rates = metrics.rates(backend.key());
     let result = match metric {
       StatusMetrics::BackendName => backend.clone().name,
       StatusMetrics::BackendStatus => Some(backend.clone().status),
@@ -114,6 +141,11 @@ impl Status {
       StatusMetrics::CurrentSessions => Some(backend.clone().sessions.to_string()),
       StatusMetrics::ActiveServers => Some(backend.clone().active_servers.to_string()),
       StatusMetrics::BackupServers => Some(backend.clone().backup_servers.to_string()),
+      StatusMetrics::RequestRate => Some(format_rate(rates.requests)),
+      StatusMetrics::ErrorRate => Some(format_rate(rates.errors)),
+      StatusMetrics::Http5xxRate => Some(format_rate(rates.http_5xx)),
+      StatusMetrics::BytesInRate => Some(format_bytes_rate(rates.bytes_in)),
+      StatusMetrics::BytesOutRate => Some(format_bytes_rate(rates.bytes_out)),
     };
 
     log::trace!("Status::get_metric: Retrieved {:?} = {:?}", metric, result);
@@ -153,6 +185,8 @@ impl Status {
     
     log::trace!("Status::get_all_metrics: Found backend, extracting all metrics");
 
+    let rates = metrics.rates(backend.key());
+
     // Extract all metrics from the single backend instance
     vec![
       backend.clone().name,                                    // BackendName
@@ -161,6 +195,11 @@ impl Status {
       Some(backend.clone().sessions.to_string()),             // CurrentSessions
       Some(backend.clone().active_servers.to_string()),       // ActiveServers
       Some(backend.clone().backup_servers.to_string()),       // BackupServers
+      Some(format_rate(rates.requests)),                      // RequestRate
+      Some(format_rate(rates.errors)),                        // ErrorRate
+      Some(format_rate(rates.http_5xx)),                      // Http5xxRate
+      Some(format_bytes_rate(rates.bytes_in)),                // BytesInRate
+      Some(format_bytes_rate(rates.bytes_out)),               // BytesOutRate
     ]
   }
 }
@@ -176,6 +215,16 @@ impl Component for Status {
         self.update_cached_metrics();
         Ok(None)
       },
+      Action::ActiveEndpoint(endpoint) => {
+        self.endpoint = Some(endpoint);
+        Ok(None)
+      },
+      Action::CollectorState(endpoint, state) => {
+        if self.endpoint.as_ref() == Some(&endpoint) {
+          self.stale = state.is_stale();
+        }
+        Ok(None)
+      },
       Action::UseItem(backend_name) => {
         log::info!("Status::update: Switching to backend: {}", backend_name);
         self.selected_backend = Some(backend_name);
@@ -196,15 +245,19 @@ impl Component for Status {
 
     let border = Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Yellow));
 
+    let columns = STATUS_METRICS.len().div_ceil(STATUS_ROWS) as u32;
     let sides = Layout::default()
       .direction(Direction::Horizontal)
-      .constraints(vec![Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+      .constraints(vec![Constraint::Ratio(1, columns); columns as usize])
       .split(border.inner(rect));
 
-    let lengths = vec![Constraint::Length(1); 3];
-
-    let left = Layout::default().direction(Direction::Vertical).constraints(lengths.clone()).split(sides[0]);
-    let right = Layout::default().direction(Direction::Vertical).constraints(lengths.clone()).split(sides[1]);
+    let lengths = vec![Constraint::Length(1); STATUS_ROWS];
+    let cells: Vec<Rect> = sides
+      .iter()
+      .flat_map(|side| {
+        Layout::default().direction(Direction::Vertical).constraints(lengths.clone()).split(*side).to_vec()
+      })
+      .collect();
 
     f.render_widget(border.clone(), rect);
     
@@ -225,13 +278,13 @@ impl Component for Status {
 
       let text = Paragraph::new(Line::from(vec![key, value]));
 
-      if i < 3 {
-        f.render_widget(text, left[i]);
-      } else {
-        f.render_widget(text, right[i - 3]);
-      }
+      f.render_widget(text, cells[i]);
     }
     
+    if self.stale {
+      grey_out(f, rect);
+    }
+
     let elapsed = start.elapsed();
     if elapsed.as_millis() > 1 {
       log::debug!("Status::draw: Draw took: {:?} (render: {:?}) - used cached metrics", 
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use chrono::Local;
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, Frame};
+use crate::{action::Action, stats::collector::CollectorState};
+
+/// One-line summary of the active endpoint's collector: when data was last fetched, or why it couldn't be.
+#[derive(Default)]
+pub struct StatusBar {
+  endpoint: Option<String>,
+  states: HashMap<String, CollectorState>,
+}
+
+impl StatusBar {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn line(&self) -> Line<'static> {
+    let endpoint = match &self.endpoint {
+      Some(endpoint) => endpoint.clone(),
+      None => return Line::default(),
+    };
+
+    match self.states.get(&endpoint).cloned().unwrap_or_default() {
+      CollectorState::Connecting => Line::from(vec![" ● ".yellow(), format!("{} connecting...", endpoint).into()]),
+      CollectorState::Connected { updated, next } => {
+        let every = (next - updated).num_milliseconds() as f64 / 1000.0;
+        let remaining = (next - Local::now()).num_milliseconds().max(0) as f64 / 1000.0;
+        Line::from(vec![
+          " ● ".green(),
+          format!("{} connected", endpoint).into(),
+          format!(", updated {}, next refresh in {:.0}s (every {}s)", updated.format("%H:%M:%S"), remaining.ceil(), every)
+            .dim(),
+        ])
+      },
+      CollectorState::Stale { since, error, retry } => {
+        let since = match since {
+          Some(since) => format!("stale since {}", since.format("%H:%M:%S")),
+          None => "no data yet".to_string(),
+        };
+        let retry = (retry - Local::now()).num_seconds().max(0);
+        Line::from(vec![
+          " ● ".red(),
+          format!("{} disconnected, {}", endpoint, since).red().bold(),
+          format!(" ({}), retrying in {}s", error, retry).dim(),
+        ])
+      },
+    }
+  }
+}
+
+impl Component for StatusBar {
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    match action {
+      Action::ActiveEndpoint(endpoint) => self.endpoint = Some(endpoint),
+      Action::CollectorState(endpoint, state) => {
+        self.states.insert(endpoint, state);
+      },
+      _ => {},
+    }
+    Ok(None)
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    f.render_widget(Paragraph::new(self.line()), area);
+    Ok(())
+  }
+}
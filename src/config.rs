@@ -11,7 +11,12 @@ use serde::{
 };
 use serde_json::Value as JsonValue;
 
-use crate::{action::Action, mode::Mode};
+use crate::{
+  action::Action,
+  components::columns::Column,
+  mode::Mode,
+  stats::{data::ResourceType, typed::StatFormat},
+};
 
 const CONFIG: &str = include_str!("../.config/config.json5");
 
@@ -33,6 +38,21 @@ pub struct Config {
   pub styles: Styles,
   #[serde(default)]
   pub paths: Paths,
+  #[serde(default, deserialize_with = "unique_endpoints")]
+  pub endpoints: Vec<Endpoint>,
+  /// Seconds between polls, `stats::collector::DEFAULT_REFRESH_INTERVAL` when unset.
+  #[serde(default)]
+  pub refresh_interval: Option<f64>,
+  /// Seconds of snapshots to keep per endpoint, `stats::history::DEFAULT_RETENTION` when unset. Snapshots older
+  /// than five minutes are kept at one per minute.
+  #[serde(default)]
+  pub history_retention: Option<u64>,
+  /// Format to request `show stat` in from stats sockets.
+  #[serde(default)]
+  pub stat_format: StatFormat,
+  /// Columns of the main table for each resource type, after the name.
+  #[serde(default)]
+  pub columns: HashMap<ResourceType, Vec<Column>>,
 }
 
 impl Config {
@@ -74,10 +94,14 @@ impl Config {
     for (mode, default_styles) in default_config.styles.iter() {
       let user_styles = cfg.styles.entry(*mode).or_default();
       for (style_key, style) in default_styles.iter() {
-        user_styles.entry(style_key.clone()).or_insert_with(|| style.clone());
+        user_styles.entry(style_key.clone()).or_insert_with(|| *style);
       }
     }
 
+    for (resource, default_columns) in default_config.columns {
+      cfg.columns.entry(resource).or_insert(default_columns);
+    }
+
     Ok(cfg)
   }
 }
@@ -204,7 +228,7 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
       char = format!("f({c})");
       &char
     },
-    KeyCode::Char(c) if c == ' ' => "space",
+    KeyCode::Char(' ') => "space",
     KeyCode::Char(c) => {
       char = c.to_string();
       &char
@@ -389,6 +413,27 @@ fn parse_color(s: &str) -> Option<Color> {
   }
 }
 
+/// A named HAProxy instance. `address` takes anything `paths.socket` accepts.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct Endpoint {
+  pub name: String,
+  pub address: String,
+}
+
+/// Endpoints are told apart by name, so two with the same one would share a collector and its stats.
+fn unique_endpoints<'de, D>(deserializer: D) -> Result<Vec<Endpoint>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  let endpoints = Vec::<Endpoint>::deserialize(deserializer)?;
+  for (i, endpoint) in endpoints.iter().enumerate() {
+    if endpoints[..i].iter().any(|other| other.name == endpoint.name) {
+      return Err(de::Error::custom(format!("Endpoint name {:?} is used more than once", endpoint.name)));
+    }
+  }
+  Ok(endpoints)
+}
+
 #[derive(Deserialize, Clone, Debug, Deref, DerefMut)]
 pub struct Paths {
     pub socket: String,
@@ -474,6 +519,81 @@ mod tests {
     Ok(())
   }
 
+  #[test]
+  fn test_back_to_home() -> Result<()> {
+    let c = Config::new()?;
+    for mode in [Mode::Cluster, Mode::Processes, Mode::Server, Mode::Frontend, Mode::Responses] {
+      for key in ["<q>", "<Esc>"] {
+        let action = c.keybindings.get(&mode).and_then(|keymap| keymap.get(&parse_key_sequence(key).unwrap()));
+        assert_eq!(action, Some(&Action::SwitchMode(Mode::Home)), "{} in {:?}", key, mode);
+      }
+    }
+    Ok(())
+  }
+
+  #[test]
+  fn test_endpoints() {
+    let c: Config = json5::from_str(
+      r#"{
+        "endpoints": [
+          { "name": "edge-1", "address": "tcp:10.0.0.1:9999" },
+          { "name": "edge-2", "address": "/var/run/haproxy.sock" },
+        ],
+      }"#,
+    )
+    .unwrap();
+    assert_eq!(c.endpoints.len(), 2);
+    assert_eq!(c.endpoints[0], Endpoint { name: "edge-1".to_string(), address: "tcp:10.0.0.1:9999".to_string() });
+    assert_eq!(c.refresh_interval, None);
+  }
+
+  #[test]
+  fn test_duplicate_endpoints() {
+    let error = json5::from_str::<Config>(
+      r#"{
+        "endpoints": [
+          { "name": "edge-1", "address": "tcp:10.0.0.1:9999" },
+          { "name": "edge-1", "address": "tcp:10.0.0.2:9999" },
+        ],
+      }"#,
+    )
+    .unwrap_err();
+    assert!(error.to_string().contains("Endpoint name \"edge-1\" is used more than once"), "{}", error);
+  }
+
+  #[test]
+  fn test_refresh_interval() {
+    let c: Config = json5::from_str(r#"{ "refresh_interval": 2.5 }"#).unwrap();
+    assert_eq!(c.refresh_interval, Some(2.5));
+  }
+
+  #[test]
+  fn test_stat_format() {
+    let c: Config = json5::from_str(r#"{ "stat_format": "json" }"#).unwrap();
+    assert_eq!(c.stat_format, StatFormat::Json);
+    assert_eq!(Config::default().stat_format, StatFormat::Csv);
+  }
+
+  #[test]
+  fn test_columns() -> Result<()> {
+    let c = Config::new()?;
+    for resource in [ResourceType::Frontend, ResourceType::Backend, ResourceType::Server, ResourceType::Combined] {
+      assert!(!c.columns[&resource].is_empty(), "no default columns for {}", resource);
+    }
+
+    let c: Config = json5::from_str(
+      r#"{
+        "columns": {
+          "Server": [{ "title": "Down for", "field": "downtime", "format": "seconds", "hidden": true }],
+        },
+      }"#,
+    )?;
+    let column = &c.columns[&ResourceType::Server][0];
+    assert_eq!(column.format, crate::components::columns::ColumnFormat::Seconds);
+    assert!(column.hidden);
+    Ok(())
+  }
+
   #[test]
   fn test_simple_keys() {
     assert_eq!(parse_key_event("a").unwrap(), KeyEvent::new(KeyCode::Char('a'), KeyModifiers::empty()));
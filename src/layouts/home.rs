@@ -4,7 +4,7 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect, Size};
 
 use crate::{
   action::{Action, TypingMode, MovementMode},
-  components::{fps::FpsCounter, items::Items, menu::Menu, Component},
+  components::{fps::FpsCounter, items::Items, menu::Menu, statusbar::StatusBar, Component},
   config::Config,
   tui::{Event, Frame},
 };
@@ -15,12 +15,20 @@ pub struct HomeLayout {
   action_handler: Option<tokio::sync::mpsc::UnboundedSender<Action>>,
 }
 
+impl Default for HomeLayout {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl HomeLayout {
   pub fn new() -> Self {
-    let mut components: Vec<Box<dyn Component>> = Vec::new();
-    components.push(Box::new(Menu::new()));
-    components.push(Box::new(Items::new()));
-    components.push(Box::new(FpsCounter::new()));
+    let components: Vec<Box<dyn Component>> = vec![
+      Box::new(Menu::new()),
+      Box::new(Items::new()),
+      Box::new(StatusBar::new()),
+      Box::new(FpsCounter::new()),
+    ];
     Self {
       components,
       action_handler: None,
@@ -28,6 +36,7 @@ impl HomeLayout {
         Constraint::Length(6),
         Constraint::Min(0),
         Constraint::Length(1),
+        Constraint::Length(1),
       ]),
     }
   }
@@ -112,7 +121,7 @@ impl Component for HomeLayout {
   fn handle_key_events(&mut self, typing_mode: TypingMode, key: KeyEvent) -> Result<Option<Action>> {
     let mut actions: Vec<Action> = Vec::new();
     for component in self.components.iter_mut() {
-      if let Some(action) = component.handle_key_events(typing_mode.clone(), key.clone())? {
+      if let Some(action) = component.handle_key_events(typing_mode, key)? {
         actions.push(action);
       }
     }
@@ -4,7 +4,10 @@ use ratatui::layout::{Constraint, Direction, Layout, Rect, Size};
 
 use crate::{
   action::{Action, TypingMode, MovementMode},
-  components::{fps::FpsCounter, items::Items, menu::Menu, status::Status, Component, config::ConfigView},
+  components::{
+    charts::Charts, fps::FpsCounter, items::Items, latency::LatencyBreakdown, menu::Menu, status::Status,
+    statusbar::StatusBar, Component, config::ConfigView,
+  },
   config::Config,
   tui::{Event, Frame}, mode::Mode,
 };
@@ -15,17 +18,30 @@ pub struct InfoLayout {
   action_handler: Option<tokio::sync::mpsc::UnboundedSender<Action>>,
 }
 
+impl Default for InfoLayout {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
 impl InfoLayout {
   pub fn new() -> Self {
-    let mut components: Vec<Box<dyn Component>> = Vec::new();
-    components.push(Box::new(Status::new()));
-    components.push(Box::new(ConfigView::default()));
+    let components: Vec<Box<dyn Component>> = vec![
+      Box::new(Status::new()),
+      Box::new(LatencyBreakdown::new()),
+      Box::new(Charts::new()),
+      Box::new(ConfigView::default()),
+      Box::new(StatusBar::new()),
+    ];
     Self {
       components,
       action_handler: None,
       layout: Layout::default().direction(Direction::Vertical).constraints(vec![
         Constraint::Length(5),
+        Constraint::Percentage(20),
+        Constraint::Percentage(40),
         Constraint::Min(0),
+        Constraint::Length(1),
       ]),
     }
   }
@@ -115,7 +131,7 @@ impl Component for InfoLayout {
 
     let mut actions: Vec<Action> = Vec::new();
     for component in self.components.iter_mut() {
-      if let Some(action) = component.handle_key_events(typing_mode.clone(), key.clone())? {
+      if let Some(action) = component.handle_key_events(typing_mode, key)? {
         actions.push(action);
       }
     }
@@ -0,0 +1,132 @@
+use color_eyre::eyre::Result;
+use crossterm::event::KeyEvent;
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+
+use crate::{
+  action::{Action, MovementMode, TypingMode},
+  components::{config::ConfigView, fps::FpsCounter, statusbar::StatusBar, Component},
+  config::Config,
+  tui::{Event, Frame},
+};
+
+/// Components stacked on top of each other, each getting the slice of the screen its constraint gives it.
+/// Everything is passed on to every component. Keys such as q and Esc for going back are left to the
+/// keybindings of the mode.
+pub struct StackedLayout {
+  pub components: Vec<Box<dyn Component>>,
+  pub layout: Layout,
+  action_handler: Option<tokio::sync::mpsc::UnboundedSender<Action>>,
+}
+
+impl StackedLayout {
+  pub fn new(components: Vec<Box<dyn Component>>, constraints: Vec<Constraint>) -> Self {
+    let layout = Layout::default().direction(Direction::Vertical).constraints(constraints);
+    Self { components, layout, action_handler: None }
+  }
+
+  /// `component` on the whole screen, above the status bar and FPS counter.
+  pub fn full_screen(component: Box<dyn Component>) -> Self {
+    Self::new(
+      vec![component, Box::new(StatusBar::new()), Box::new(FpsCounter::new())],
+      vec![Constraint::Min(0), Constraint::Length(1), Constraint::Length(1)],
+    )
+  }
+
+  /// `component` in the top `height` rows, with the config it comes from below and the status bar at the bottom.
+  pub fn details(component: Box<dyn Component>, height: u16) -> Self {
+    Self::new(
+      vec![component, Box::new(ConfigView::default()), Box::new(StatusBar::new())],
+      vec![Constraint::Length(height), Constraint::Min(0), Constraint::Length(1)],
+    )
+  }
+
+  fn send_all(&self, actions: Vec<Action>) -> Result<()> {
+    if let Some(action_handler) = &self.action_handler {
+      for action in actions {
+        action_handler.send(action)?;
+      }
+    }
+    Ok(())
+  }
+}
+
+impl Component for StackedLayout {
+  fn init(&mut self, area: Rect) -> Result<()> {
+    let layout = self.layout.split(area);
+    // Give each element a slice of the screen
+    for (i, component) in self.components.iter_mut().enumerate() {
+      component.init(layout[i])?;
+    }
+    Ok(())
+  }
+
+  fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+    let layout = self.layout.split(area);
+    // Give each element a slice of the screen
+    for (i, component) in self.components.iter_mut().enumerate() {
+      component.draw(f, layout[i])?;
+    }
+    Ok(())
+  }
+
+  fn register_action_handler(&mut self, tx: tokio::sync::mpsc::UnboundedSender<Action>) -> Result<()> {
+    self.action_handler = Some(tx.clone());
+
+    for component in self.components.iter_mut() {
+      component.register_action_handler(tx.clone())?;
+    }
+    Ok(())
+  }
+
+  fn register_config_handler(&mut self, config: Config) -> Result<()> {
+    for component in self.components.iter_mut() {
+      component.register_config_handler(config.clone())?;
+    }
+    Ok(())
+  }
+
+  fn move_down(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    for component in self.components.iter_mut() {
+      component.move_down(mode.clone())?;
+    }
+    Ok(None)
+  }
+
+  fn move_up(&mut self, mode: MovementMode) -> Result<Option<Action>> {
+    for component in self.components.iter_mut() {
+      component.move_up(mode.clone())?;
+    }
+    Ok(None)
+  }
+
+  fn handle_events(&mut self, event: Option<Event>) -> Result<Option<Action>> {
+    let r = match event {
+      Some(crate::tui::Event::ModeKey(typing_mode, key_event)) => self.handle_key_events(typing_mode, key_event)?,
+      Some(crate::tui::Event::Mouse(mouse_event)) => self.handle_mouse_events(mouse_event)?,
+      _ => None,
+    };
+    Ok(r)
+  }
+
+  fn update(&mut self, action: Action) -> Result<Option<Action>> {
+    let mut actions: Vec<Action> = Vec::new();
+    for component in self.components.iter_mut() {
+      if let Some(action) = component.update(action.clone())? {
+        actions.push(action);
+      }
+    }
+    self.send_all(actions)?;
+    Ok(None)
+  }
+
+  fn handle_key_events(&mut self, typing_mode: TypingMode, key: KeyEvent) -> Result<Option<Action>> {
+    let mut actions: Vec<Action> = Vec::new();
+    for component in self.components.iter_mut() {
+      if let Some(action) = component.handle_key_events(typing_mode, key)? {
+        actions.push(action);
+      }
+    }
+    self.send_all(actions)?;
+    Ok(None)
+  }
+}
@@ -5,5 +5,10 @@ pub enum Mode {
   #[default]
   Home,
   Info,
+  Cluster,
+  Processes,
+  Server,
+  Frontend,
+  Responses,
 }
 
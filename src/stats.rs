@@ -1,3 +1,11 @@
+pub mod admin;
+pub mod collector;
 pub mod data;
+pub mod history;
+pub mod http;
+pub mod latency;
+pub mod master;
 pub mod metrics;
+pub mod rates;
 pub mod socket;
+pub mod typed;
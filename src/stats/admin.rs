@@ -0,0 +1,363 @@
+use std::{
+  fmt,
+  net::{IpAddr, SocketAddr},
+  str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use strum::Display;
+
+/// Beginnings of the messages HAProxy answers a command it refused with, compared case-insensitively. Anything
+/// else, including no answer at all, means it was applied.
+const ERRORS: [&str; 20] = [
+  "No such",
+  "Unknown ",
+  "Permission denied",
+  "Require ",
+  "Invalid ",
+  "Missing ",
+  "Cannot ",
+  "Can't ",
+  "Unsupported ",
+  "Failed ",
+  // set server <backend>/<server> weight, and set weight
+  "Backend is using a static LB algorithm",
+  "Relative weight ",
+  "Absolute weight ",
+  "Weight ",
+  // set server <backend>/<server> with an argument it doesn't take
+  "'set server",
+  "Server is not ",
+  // set server <backend>/<server> health|agent on a server without that check
+  "Health checks are not enabled",
+  "Agent checks are not enabled",
+  // enable/disable/shutdown frontend
+  "Frontend is already ",
+  "Frontend was ",
+];
+
+/// Administrative state of a server, as set with `set server <backend>/<server> state`.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum ServerState {
+  /// Back in rotation.
+  Ready,
+  /// No new connections unless they're persistent, existing ones finish.
+  Drain,
+  /// Out of rotation, health checks stop.
+  Maint,
+}
+
+/// The two kinds of checks a server can have.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum Check {
+  /// The regular health check.
+  Health,
+  /// The agent check, reporting a weight or state from the server itself.
+  Agent,
+}
+
+/// Operational state to force a check into, as with `set server <backend>/<server> health`.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum CheckState {
+  Up,
+  /// Up, but only for persistent connections. Health checks only.
+  Stopping,
+  Down,
+}
+
+/// What can be done to a frontend as a whole.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[strum(serialize_all = "lowercase")]
+pub enum FrontendCommand {
+  /// Start accepting connections again after `disable`.
+  Enable,
+  /// Stop accepting new connections, keeping the ports bound so it can be enabled again.
+  Disable,
+  /// Release the ports for good. Only a reload brings the frontend back.
+  Shutdown,
+}
+
+/// A new address for a server, with or without a port. Without one, the server keeps its port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerAddr {
+  pub ip: IpAddr,
+  pub port: Option<u16>,
+}
+
+impl FromStr for ServerAddr {
+  type Err = String;
+
+  /// Takes `10.0.0.2`, `10.0.0.2:8080`, `2001:db8::2` or `[2001:db8::2]:8080`.
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    let input = input.trim();
+    if let Ok(ip) = input.parse::<IpAddr>() {
+      return Ok(ServerAddr { ip, port: None });
+    }
+    match input.parse::<SocketAddr>() {
+      Ok(addr) if addr.port() == 0 => Err("Port 0 isn't a port a server can listen on".to_string()),
+      Ok(addr) => Ok(ServerAddr { ip: addr.ip(), port: Some(addr.port()) }),
+      Err(_) => Err(format!("'{}' isn't an IP address, optionally followed by :port", input)),
+    }
+  }
+}
+
+/// Highest weight HAProxy takes for a server.
+pub const MAX_WEIGHT: u32 = 256;
+
+/// Highest percentage of the initial weight HAProxy takes for a server, i.e. `MAX_WEIGHT` times 100%. The weight
+/// that comes out is capped at `MAX_WEIGHT` too.
+pub const MAX_WEIGHT_PERCENT: u32 = MAX_WEIGHT * 100;
+
+/// A server weight as typed in: either as it is, or as a percentage of the server's initial weight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Weight {
+  Absolute(u32),
+  Percent(u32),
+}
+
+impl FromStr for Weight {
+  type Err = String;
+
+  fn from_str(input: &str) -> Result<Self, Self::Err> {
+    let input = input.trim();
+    let invalid = || {
+      let (max, max_percent) = (MAX_WEIGHT, MAX_WEIGHT_PERCENT);
+      format!("'{}' isn't a weight, use 0 to {} or 0% to {}% of the initial weight", input, max, max_percent)
+    };
+    match input.strip_suffix('%') {
+      Some(percent) => match percent.parse() {
+        Ok(percent) if percent <= MAX_WEIGHT_PERCENT => Ok(Weight::Percent(percent)),
+        _ => Err(invalid()),
+      },
+      None => match input.parse() {
+        Ok(weight) if weight <= MAX_WEIGHT => Ok(Weight::Absolute(weight)),
+        _ => Err(invalid()),
+      },
+    }
+  }
+}
+
+impl fmt::Display for Weight {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Weight::Absolute(weight) => write!(f, "{}", weight),
+      Weight::Percent(percent) => write!(f, "{}%", percent),
+    }
+  }
+}
+
+/// A command that changes HAProxy's running state, along with how to ask the user about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AdminCommand {
+  /// What the command does in plain words, e.g. `Drain app/app1`.
+  pub description: String,
+  /// The CLI command, e.g. `set server app/app1 state drain`.
+  pub command: String,
+}
+
+impl AdminCommand {
+  pub fn set_server_state(backend: &str, server: &str, state: ServerState) -> AdminCommand {
+    let description = match state {
+      ServerState::Ready => format!("Put {}/{} back in rotation (ready)", backend, server),
+      ServerState::Drain => format!("Drain {}/{}", backend, server),
+      ServerState::Maint => format!("Put {}/{} into maintenance", backend, server),
+    };
+    AdminCommand { description, command: format!("set server {}/{} state {}", backend, server, state) }
+  }
+
+  pub fn set_server_weight(backend: &str, server: &str, weight: Weight) -> AdminCommand {
+    let description = match weight {
+      Weight::Absolute(weight) => format!("Set the weight of {}/{} to {}", backend, server, weight),
+      Weight::Percent(percent) => {
+        format!("Set the weight of {}/{} to {}% of its initial weight", backend, server, percent)
+      },
+    };
+    AdminCommand { description, command: format!("set server {}/{} weight {}", backend, server, weight) }
+  }
+
+  /// `enable health`, `disable agent` and so on, which start or stop a check without touching the server's state.
+  pub fn enable_check(backend: &str, server: &str, check: Check, enable: bool) -> AdminCommand {
+    let (action, description) = if enable { ("enable", "Enable") } else { ("disable", "Disable") };
+    AdminCommand {
+      description: format!("{} the {} check of {}/{}", description, check, backend, server),
+      command: format!("{} {} {}/{}", action, check, backend, server),
+    }
+  }
+
+  /// Forces the result of a check until the next one runs. Agent checks can't be set to `stopping`.
+  pub fn set_check_state(backend: &str, server: &str, check: Check, state: CheckState) -> AdminCommand {
+    AdminCommand {
+      description: format!("Mark the {} check of {}/{} as {}", check, backend, server, state),
+      command: format!("set server {}/{} {} {}", backend, server, check, state),
+    }
+  }
+
+  /// Everything that can be done to a server's checks, in the order they're offered.
+  pub fn server_checks(backend: &str, server: &str) -> Vec<AdminCommand> {
+    let mut commands = Vec::new();
+    for check in [Check::Health, Check::Agent] {
+      commands.push(AdminCommand::enable_check(backend, server, check, true));
+      commands.push(AdminCommand::enable_check(backend, server, check, false));
+      for state in [CheckState::Up, CheckState::Stopping, CheckState::Down] {
+        if !(check == Check::Agent && state == CheckState::Stopping) {
+          commands.push(AdminCommand::set_check_state(backend, server, check, state));
+        }
+      }
+    }
+    commands
+  }
+
+  pub fn frontend(frontend: &str, command: FrontendCommand) -> AdminCommand {
+    let description = match command {
+      FrontendCommand::Enable => format!("Enable frontend {}", frontend),
+      FrontendCommand::Disable => format!("Disable frontend {}, it stops accepting connections", frontend),
+      FrontendCommand::Shutdown => format!("Shut down frontend {} until HAProxy is reloaded", frontend),
+    };
+    AdminCommand { description, command: format!("{} frontend {}", command, frontend) }
+  }
+
+  pub fn set_frontend_maxconn(frontend: &str, maxconn: u32) -> AdminCommand {
+    AdminCommand {
+      description: format!("Set maxconn of frontend {} to {}", frontend, maxconn),
+      command: format!("set maxconn frontend {} {}", frontend, maxconn),
+    }
+  }
+
+  pub fn set_server_addr(backend: &str, server: &str, addr: ServerAddr) -> AdminCommand {
+    let (description, command) = match addr.port {
+      Some(port) => (
+        format!("Move {}/{} to {} port {}", backend, server, addr.ip, port),
+        format!("set server {}/{} addr {} port {}", backend, server, addr.ip, port),
+      ),
+      None => (
+        format!("Move {}/{} to {}, keeping its port", backend, server, addr.ip),
+        format!("set server {}/{} addr {}", backend, server, addr.ip),
+      ),
+    };
+    AdminCommand { description, command }
+  }
+
+  /// Back to the weight from the config, which HAProxy calls the initial weight.
+  pub fn reset_server_weight(backend: &str, server: &str) -> AdminCommand {
+    AdminCommand {
+      description: format!("Reset the weight of {}/{} to its initial weight", backend, server),
+      ..AdminCommand::set_server_weight(backend, server, Weight::Percent(100))
+    }
+  }
+}
+
+/// What one process answered to a command, with the worker's PID when it was sent through a master CLI.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerResponse {
+  pub pid: Option<u32>,
+  pub response: Result<String, String>,
+}
+
+/// Tells HAProxy's answer to a command apart into what it said when it applied the command and why it didn't.
+pub fn parse_response(response: &str) -> Result<String, String> {
+  let response = response.trim();
+  let lowercase = response.to_lowercase();
+  if ERRORS.iter().any(|error| lowercase.starts_with(&error.to_lowercase())) {
+    Err(response.to_string())
+  } else {
+    Ok(response.to_string())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_set_server_state() {
+    let command = AdminCommand::set_server_state("app", "app1", ServerState::Drain);
+    assert_eq!(command.command, "set server app/app1 state drain");
+    assert_eq!(command.description, "Drain app/app1");
+    let command = AdminCommand::set_server_state("app", "app1", ServerState::Maint);
+    assert_eq!(command.command, "set server app/app1 state maint");
+  }
+
+  #[test]
+  fn test_weight() {
+    assert_eq!("42".parse(), Ok(Weight::Absolute(42)));
+    assert_eq!(" 50% ".parse(), Ok(Weight::Percent(50)));
+    assert_eq!("256".parse(), Ok(Weight::Absolute(256)));
+    assert!("257".parse::<Weight>().is_err());
+    assert!("-1".parse::<Weight>().is_err());
+    assert!("half".parse::<Weight>().is_err());
+    assert!("%".parse::<Weight>().is_err());
+    assert_eq!("25600%".parse(), Ok(Weight::Percent(25600)));
+    assert!("25601%".parse::<Weight>().is_err());
+
+    let command = AdminCommand::set_server_weight("app", "app1", Weight::Percent(50));
+    assert_eq!(command.command, "set server app/app1 weight 50%");
+    let command = AdminCommand::reset_server_weight("app", "app1");
+    assert_eq!(command.command, "set server app/app1 weight 100%");
+    assert_eq!(command.description, "Reset the weight of app/app1 to its initial weight");
+  }
+
+  #[test]
+  fn test_server_addr() {
+    let addr = |input: &str| input.parse::<ServerAddr>().map(|addr| AdminCommand::set_server_addr("app", "app1", addr));
+    assert_eq!(addr("10.0.0.2:8080").unwrap().command, "set server app/app1 addr 10.0.0.2 port 8080");
+    assert_eq!(addr(" 10.0.0.2 ").unwrap().command, "set server app/app1 addr 10.0.0.2");
+    assert_eq!(addr("[2001:db8::2]:443").unwrap().command, "set server app/app1 addr 2001:db8::2 port 443");
+    assert_eq!(addr("2001:db8::2").unwrap().command, "set server app/app1 addr 2001:db8::2");
+    assert!(addr("10.0.0.2:0").is_err());
+    assert!(addr("10.0.0.2:99999").is_err());
+    assert!(addr("10.0.0.256").is_err());
+    assert!(addr("app2.internal:80").is_err());
+  }
+
+  #[test]
+  fn test_server_checks() {
+    let commands: Vec<String> =
+      AdminCommand::server_checks("app", "app1").into_iter().map(|command| command.command).collect();
+    assert_eq!(commands, vec![
+      "enable health app/app1",
+      "disable health app/app1",
+      "set server app/app1 health up",
+      "set server app/app1 health stopping",
+      "set server app/app1 health down",
+      "enable agent app/app1",
+      "disable agent app/app1",
+      "set server app/app1 agent up",
+      "set server app/app1 agent down",
+    ]);
+    let command = AdminCommand::enable_check("app", "app1", Check::Agent, false);
+    assert_eq!(command.description, "Disable the agent check of app/app1");
+  }
+
+  #[test]
+  fn test_frontend() {
+    assert_eq!(AdminCommand::frontend("web", FrontendCommand::Disable).command, "disable frontend web");
+    assert_eq!(AdminCommand::frontend("web", FrontendCommand::Shutdown).command, "shutdown frontend web");
+    assert_eq!(AdminCommand::set_frontend_maxconn("web", 1000).command, "set maxconn frontend web 1000");
+  }
+
+  #[test]
+  fn test_parse_response() {
+    assert_eq!(parse_response("\n"), Ok("".to_string()));
+    assert_eq!(parse_response("No such server.\n"), Err("No such server.".to_string()));
+    assert_eq!(parse_response("Permission denied\n"), Err("Permission denied".to_string()));
+    assert_eq!(parse_response("Unknown command. Please enter one of the following commands only :\n  help"),
+      Err("Unknown command. Please enter one of the following commands only :\n  help".to_string()));
+    assert_eq!(parse_response("IP changed from '10.0.0.11' to '10.0.0.21' by 'stats socket command'\n"),
+      Ok("IP changed from '10.0.0.11' to '10.0.0.21' by 'stats socket command'".to_string()));
+  }
+
+  #[test]
+  fn test_parse_rejected_weight() {
+    let response = "Backend is using a static LB algorithm and only accepts weights '0%' and '100%'.\n";
+    assert_eq!(parse_response(response), Err(response.trim().to_string()));
+    assert!(parse_response("Relative weight can only be set between 0 and 100% inclusive.\n").is_err());
+    assert!(parse_response("cannot change health on a tracking server.\n").is_err());
+    assert!(parse_response("agent checks are not enabled on this server.\n").is_err());
+    assert!(parse_response("'set server <srv> state' expects 'ready', 'drain' and 'maint'.\n").is_err());
+  }
+}
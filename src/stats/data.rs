@@ -1,7 +1,23 @@
+use std::collections::BTreeMap;
+
 use csv::{StringRecord, Trim};
 use serde::{Deserialize, Serialize};
 use strum::Display;
 
+/// Columns of `show stat` that map onto `HaproxyStat`, in field order.
+const FIELDS: [&str; 100] = [
+  "pxname", "svname", "qcur", "qmax", "scur", "smax", "slim", "stot", "bin", "bout", "dreq", "dresp", "ereq", "econ",
+  "eresp", "wretr", "wredis", "status", "weight", "act", "bck", "chkfail", "chkdown", "lastchg", "downtime", "qlimit",
+  "pid", "iid", "sid", "throttle", "lbtot", "tracked", "type", "rate", "rate_lim", "rate_max", "check_status",
+  "check_code", "check_duration", "hrsp_1xx", "hrsp_2xx", "hrsp_3xx", "hrsp_4xx", "hrsp_5xx", "hrsp_other", "hanafail",
+  "req_rate", "req_rate_max", "req_tot", "cli_abrt", "srv_abrt", "comp_in", "comp_out", "comp_byp", "comp_rsp",
+  "lastsess", "last_chk", "last_agt", "qtime", "ctime", "rtime", "ttime", "agent_status", "agent_code",
+  "agent_duration", "check_desc", "agent_desc", "check_rise", "check_fall", "check_health", "agent_rise", "agent_fall",
+  "agent_health", "addr", "cookie", "mode", "algo", "conn_rate", "conn_rate_max", "conn_tot", "intercepted", "dcon",
+  "dses", "wrew", "connect", "reuse", "cache_lookups", "cache_hits", "srv_icur", "src_ilim", "qtime_max", "ctime_max",
+  "rtime_max", "ttime_max", "eint", "idle_conn_cur", "safe_conn_cur", "used_conn_cur", "need_conn_est", "uweight",
+];
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, PartialOrd, Clone, Default)]
 pub struct HaproxyStat {
   pub pxname: Option<String>,
@@ -104,30 +120,58 @@ pub struct HaproxyStat {
   pub used_conn_cur: Option<i64>,
   pub need_conn_est: Option<i64>,
   pub uweight: Option<i64>,
+  /// Columns this version of HAProxy reports that have no field above, keyed by their header name.
+  #[serde(default)]
+  pub extra: BTreeMap<String, String>,
 }
 
 impl HaproxyStat {
+  /// Parses `show stat` CSV output. Columns are matched by the names in the `# pxname,svname,...` header, so
+  /// versions that report fewer columns leave the missing fields empty and newer columns end up in `extra`.
   pub fn parse_csv(csv: &str) -> Result<Vec<HaproxyStat>, csv::Error> {
     let mut records = Vec::new();
     // log::trace!("csv: {}", csv);
     let mut rdr =
       csv::ReaderBuilder::new().has_headers(true).trim(Trim::All).flexible(true).from_reader(csv.as_bytes());
+
+    let headers: Vec<String> =
+      rdr.headers()?.iter().map(|header| header.trim_start_matches('#').trim().to_string()).collect();
+    let positions: Vec<Option<usize>> =
+      FIELDS.iter().map(|field| headers.iter().position(|header| header == field)).collect();
+
     for result in rdr.records() {
-      let mut fields = result?;
-      for _ in fields.len()..100 {
-        fields.push_field("");
-      }
+      let fields = result?;
+
+      let known: StringRecord =
+        positions.iter().map(|position| position.and_then(|i| fields.get(i)).unwrap_or("")).collect();
+      let mut stat: HaproxyStat = known.deserialize(None)?;
 
-      let stat: HaproxyStat = fields.deserialize(None)?;
+      stat.extra = headers
+        .iter()
+        .zip(fields.iter())
+        .filter(|(header, _)| !header.is_empty() && !FIELDS.contains(&header.as_str()))
+        .map(|(header, value)| (header.clone(), value.to_string()))
+        .collect();
 
       records.push(stat);
     }
 
     Ok(records)
   }
+
+  /// Builds a stat from `(column, value)` pairs, as reported by `show stat typed` and `show stat json`.
+  pub fn from_columns<'a>(columns: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<HaproxyStat, csv::Error> {
+    let mut columns: BTreeMap<&str, &str> = columns.into_iter().collect();
+
+    let known: StringRecord = FIELDS.iter().map(|field| columns.remove(field).unwrap_or("")).collect();
+    let mut stat: HaproxyStat = known.deserialize(None)?;
+    stat.extra = columns.into_iter().map(|(column, value)| (column.to_string(), value.to_string())).collect();
+
+    Ok(stat)
+  }
 }
 
-#[derive(Default, Debug, PartialEq, Clone, Copy, Display, Serialize, Deserialize)]
+#[derive(Default, Debug, PartialEq, Eq, Hash, Clone, Copy, Display, Serialize, Deserialize)]
 pub enum ResourceType {
   Frontend,
   Backend,
@@ -143,3 +187,114 @@ pub enum StatusType {
     Failing,
     Healthy,
 }
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn parse(csv: &str) -> Vec<HaproxyStat> {
+    let stats = HaproxyStat::parse_csv(csv).unwrap();
+    assert_eq!(stats.len(), 5);
+    stats
+  }
+
+  fn find<'a>(stats: &'a [HaproxyStat], pxname: &str, svname: &str) -> &'a HaproxyStat {
+    stats.iter().find(|stat| stat.pxname.as_deref() == Some(pxname) && stat.svname.as_deref() == Some(svname)).unwrap()
+  }
+
+  /// Columns every supported version reports.
+  fn assert_common(stats: &[HaproxyStat]) {
+    let web = find(stats, "web", "FRONTEND");
+    assert_eq!(web.resource_type, Some(0));
+    assert_eq!(web.status.as_deref(), Some("OPEN"));
+    assert_eq!(web.scur, Some(3));
+    assert_eq!(web.req_tot, Some(1520.0));
+
+    let app1 = find(stats, "app", "app1");
+    assert_eq!(app1.resource_type, Some(2));
+    assert_eq!(app1.status.as_deref(), Some("UP"));
+    assert_eq!(app1.weight, Some(1));
+    assert_eq!(app1.check_status.as_deref(), Some("L4OK"));
+    assert_eq!(app1.addr.as_deref(), Some("10.0.0.11:8080"));
+
+    let app2 = find(stats, "app", "app2");
+    assert_eq!(app2.status.as_deref(), Some("DOWN"));
+    assert_eq!(app2.last_chk.as_deref(), Some("Connection refused"));
+
+    let app = find(stats, "app", "BACKEND");
+    assert_eq!(app.resource_type, Some(1));
+    assert_eq!(app.algo.as_deref(), Some("roundrobin"));
+  }
+
+  #[test]
+  fn test_parse_csv_1_8() {
+    let stats = parse(include_str!("../../tests/fixtures/show_stat-1.8.csv"));
+    assert_common(&stats);
+
+    // Columns added after 1.8 stay empty instead of picking up a neighbour's value
+    assert_eq!(find(&stats, "web", "FRONTEND").wrew, Some(0));
+    let app1 = find(&stats, "app", "app1");
+    assert_eq!(app1.connect, None);
+    assert_eq!(app1.uweight, None);
+    assert!(app1.extra.is_empty());
+  }
+
+  #[test]
+  fn test_parse_csv_2_0() {
+    let stats = parse(include_str!("../../tests/fixtures/show_stat-2.0.csv"));
+    assert_common(&stats);
+
+    let app1 = find(&stats, "app", "app1");
+    assert_eq!(app1.connect, Some(20));
+    assert_eq!(app1.need_conn_est, Some(1));
+    assert_eq!(app1.uweight, None);
+    assert!(app1.extra.is_empty());
+  }
+
+  #[test]
+  fn test_parse_csv_2_4() {
+    let stats = parse(include_str!("../../tests/fixtures/show_stat-2.4.csv"));
+    assert_common(&stats);
+
+    let app1 = find(&stats, "app", "app1");
+    assert_eq!(app1.uweight, Some(1));
+    assert_eq!(app1.extra.get("srid").map(String::as_str), Some("1"));
+    assert_eq!(app1.extra.get("h2_headers_rcvd").map(String::as_str), Some("0"));
+    assert_eq!(find(&stats, "app", "BACKEND").extra.get("agg_check_status").map(String::as_str), Some("1"));
+  }
+
+  #[test]
+  fn test_parse_csv_2_8() {
+    let stats = parse(include_str!("../../tests/fixtures/show_stat-2.8.csv"));
+    assert_common(&stats);
+
+    let web = find(&stats, "web", "FRONTEND");
+    assert_eq!(web.extra.get("h1sess").map(String::as_str), Some("1520"));
+    assert_eq!(web.extra.get("h1_bytes_in").map(String::as_str), Some("402110"));
+    assert!(web.extra.contains_key("proto"));
+    assert!(!web.extra.contains_key("type"));
+  }
+
+  #[test]
+  fn test_parse_csv_3_0() {
+    let stats = parse(include_str!("../../tests/fixtures/show_stat-3.0.csv"));
+    assert_common(&stats);
+
+    let app2 = find(&stats, "app", "app2");
+    assert_eq!(app2.extra.get("srid").map(String::as_str), Some("2"));
+    assert_eq!(app2.extra.get("reuse_pct").map(String::as_str), Some("0"));
+    assert_eq!(app2.extra.get("ssl_sess").map(String::as_str), Some("0"));
+  }
+
+  #[test]
+  fn test_parse_csv_reordered_columns() {
+    let csv = "# svname,pxname,type,status,scur,future_column,\nFRONTEND,web,0,OPEN,3,42,\n";
+    let stats = HaproxyStat::parse_csv(csv).unwrap();
+    assert_eq!(stats[0].pxname.as_deref(), Some("web"));
+    assert_eq!(stats[0].resource_type, Some(0));
+    assert_eq!(stats[0].scur, Some(3));
+    assert_eq!(stats[0].extra, BTreeMap::from([("future_column".to_string(), "42".to_string())]));
+  }
+}
@@ -0,0 +1,288 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::data::HaproxyStat;
+
+/// How long snapshots are kept unless configured otherwise.
+pub const DEFAULT_RETENTION: Duration = Duration::from_secs(3600);
+
+/// Snapshots older than this are thinned out to one per `DOWNSAMPLE_INTERVAL`.
+const DOWNSAMPLE_AFTER: Duration = Duration::from_secs(300);
+const DOWNSAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Upper bound on the snapshots kept regardless of retention, e.g. a day at one per minute plus the
+/// un-thinned last few minutes at a fast refresh interval.
+pub const MAX_SNAPSHOTS: usize = 2000;
+
+/// Numeric fields kept for every proxy and server in each snapshot. The rest of a row is only needed for the
+/// latest snapshot, which `HaproxyMetrics::instant` already holds.
+pub const HISTORY_FIELDS: [&str; 30] = [
+  "qcur", "qmax", "scur", "smax", "slim", "stot", "bin", "bout", "dreq", "dresp", "ereq", "econ", "eresp", "wretr",
+  "wredis", "req_tot", "hrsp_1xx", "hrsp_2xx", "hrsp_3xx", "hrsp_4xx", "hrsp_5xx", "hrsp_other", "cli_abrt",
+  "srv_abrt", "conn_tot", "lbtot", "qtime", "ctime", "rtime", "ttime",
+];
+
+/// Identifies a frontend, backend or server across snapshots by its type, proxy id and server id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct StatKey {
+  pub resource_type: i64,
+  pub iid: i64,
+  pub sid: i64,
+}
+
+impl StatKey {
+  pub fn of(stat: &HaproxyStat) -> StatKey {
+    StatKey {
+      resource_type: stat.resource_type.unwrap_or_default(),
+      iid: stat.iid.unwrap_or_default(),
+      sid: stat.sid.unwrap_or_default(),
+    }
+  }
+}
+
+fn field_value(stat: &HaproxyStat, field: &str) -> Option<f64> {
+  let int = |value: Option<i64>| value.map(|value| value as f64);
+  match field {
+    "qcur" => int(stat.qcur),
+    "qmax" => int(stat.qmax),
+    "scur" => int(stat.scur),
+    "smax" => int(stat.smax),
+    "slim" => int(stat.slim),
+    "stot" => int(stat.stot),
+    "bin" => int(stat.bin),
+    "bout" => int(stat.bout),
+    "dreq" => int(stat.dreq),
+    "dresp" => int(stat.dresp),
+    "ereq" => int(stat.ereq),
+    "econ" => int(stat.econ),
+    "eresp" => int(stat.eresp),
+    "wretr" => int(stat.wretr),
+    "wredis" => int(stat.wredis),
+    "req_tot" => stat.req_tot,
+    "hrsp_1xx" => stat.hrsp_1xx,
+    "hrsp_2xx" => stat.hrsp_2xx,
+    "hrsp_3xx" => stat.hrsp_3xx,
+    "hrsp_4xx" => stat.hrsp_4xx,
+    "hrsp_5xx" => stat.hrsp_5xx,
+    "hrsp_other" => stat.hrsp_other,
+    "cli_abrt" => int(stat.cli_abrt),
+    "srv_abrt" => int(stat.srv_abrt),
+    "conn_tot" => stat.conn_tot,
+    "lbtot" => int(stat.lbtot),
+    "qtime" => int(stat.qtime),
+    "ctime" => int(stat.ctime),
+    "rtime" => int(stat.rtime),
+    "ttime" => int(stat.ttime),
+    _ => None,
+  }
+}
+
+/// The `HISTORY_FIELDS` of one proxy or server at one point in time. Which fields HAProxy filled in is kept as
+/// a bitmask next to the plain values, which halves the size of `[Option<f64>; _]`. Values stay `f64` as byte
+/// counters outgrow the precision of an `f32` long before they wrap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+  present: u32,
+  values: [f64; HISTORY_FIELDS.len()],
+}
+
+impl Sample {
+  fn of(stat: &HaproxyStat) -> Sample {
+    let mut sample = Sample { present: 0, values: [0.0; HISTORY_FIELDS.len()] };
+    for (i, field) in HISTORY_FIELDS.iter().enumerate() {
+      if let Some(value) = field_value(stat, field) {
+        sample.present |= 1 << i;
+        sample.values[i] = value;
+      }
+    }
+    sample
+  }
+
+  /// Value of one of the `HISTORY_FIELDS`, `None` for other fields or when HAProxy left it empty.
+  pub fn get(&self, field: &str) -> Option<f64> {
+    let i = HISTORY_FIELDS.iter().position(|known| *known == field)?;
+    (self.present & (1 << i) != 0).then_some(self.values[i])
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+  pub time: DateTime<Local>,
+  pub samples: HashMap<StatKey, Sample>,
+}
+
+/// Snapshots from the last `retention`, oldest first, at most `MAX_SNAPSHOTS` of them. Snapshots are shared
+/// between clones, so handing a copy to the UI with every refresh is cheap.
+#[derive(Debug, Clone, PartialEq)]
+pub struct History {
+  retention: Duration,
+  snapshots: VecDeque<Arc<Snapshot>>,
+  /// Index of the oldest snapshot that hasn't been considered for downsampling yet.
+  dense_from: usize,
+}
+
+impl Default for History {
+  fn default() -> Self {
+    History::new(DEFAULT_RETENTION)
+  }
+}
+
+impl History {
+  pub fn new(retention: Duration) -> History {
+    History { retention, snapshots: VecDeque::new(), dense_from: 0 }
+  }
+
+  pub fn retention(&self) -> Duration {
+    self.retention
+  }
+
+  pub fn len(&self) -> usize {
+    self.snapshots.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.snapshots.is_empty()
+  }
+
+  /// Adds a snapshot taken at `time`, thins out the ones older than `DOWNSAMPLE_AFTER` and drops the ones that
+  /// have fallen out of the retention window.
+  pub fn record(&mut self, time: DateTime<Local>, stats: &[HaproxyStat]) {
+    let samples = stats.iter().map(|stat| (StatKey::of(stat), Sample::of(stat))).collect();
+    self.snapshots.push_back(Arc::new(Snapshot { time, samples }));
+    self.downsample(time);
+
+    let retention = chrono::Duration::from_std(self.retention).unwrap_or(chrono::Duration::MAX);
+    while self.snapshots.front().is_some_and(|oldest| time - oldest.time > retention)
+      || self.snapshots.len() > MAX_SNAPSHOTS
+    {
+      self.snapshots.pop_front();
+      self.dense_from = self.dense_from.saturating_sub(1);
+    }
+  }
+
+  /// Drops snapshots that have just become older than `DOWNSAMPLE_AFTER` if they're less than
+  /// `DOWNSAMPLE_INTERVAL` after the previous snapshot that was kept.
+  fn downsample(&mut self, now: DateTime<Local>) {
+    let after = chrono::Duration::from_std(DOWNSAMPLE_AFTER).unwrap_or(chrono::Duration::MAX);
+    let interval = chrono::Duration::from_std(DOWNSAMPLE_INTERVAL).unwrap_or(chrono::Duration::MAX);
+    while self.snapshots.get(self.dense_from).is_some_and(|snapshot| now - snapshot.time > after) {
+      let time = self.snapshots[self.dense_from].time;
+      if self.dense_from > 0 && time - self.snapshots[self.dense_from - 1].time < interval {
+        self.snapshots.remove(self.dense_from);
+      } else {
+        self.dense_from += 1;
+      }
+    }
+  }
+
+  pub fn snapshots(&self) -> impl DoubleEndedIterator<Item = &Snapshot> {
+    self.snapshots.iter().map(|snapshot| snapshot.as_ref())
+  }
+
+  /// Samples of one proxy or server over the last `window`, oldest first. Snapshots it's missing from, e.g.
+  /// before it was added by a reload, are skipped.
+  pub fn series(&self, key: StatKey, window: Duration) -> Vec<(DateTime<Local>, &Sample)> {
+    let latest = match self.snapshots.back() {
+      Some(latest) => latest.time,
+      None => return Vec::new(),
+    };
+    let window = chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX);
+
+    self
+      .snapshots()
+      .filter(|snapshot| latest - snapshot.time <= window)
+      .filter_map(|snapshot| snapshot.samples.get(&key).map(|sample| (snapshot.time, sample)))
+      .collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn stats(req_tot: f64) -> Vec<HaproxyStat> {
+    let mut stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    for stat in &mut stats {
+      stat.req_tot = Some(req_tot);
+    }
+    stats
+  }
+
+  #[test]
+  fn test_record_and_prune() {
+    let start = Local::now();
+    let mut history = History::new(Duration::from_secs(60));
+
+    for i in 0..10 {
+      history.record(start + chrono::Duration::seconds(i * 10), &stats(i as f64));
+    }
+    // 0s..30s have fallen out of the last minute
+    assert_eq!(history.len(), 7);
+
+    let app1 = StatKey { resource_type: 2, iid: 4, sid: 1 };
+    let series = history.series(app1, Duration::from_secs(20));
+    let values: Vec<Option<f64>> = series.iter().map(|(_, sample)| sample.get("req_tot")).collect();
+    assert_eq!(values, vec![Some(7.0), Some(8.0), Some(9.0)]);
+    assert_eq!(series[0].1.get("scur"), Some(1.0));
+    assert_eq!(series[0].1.get("pxname"), None);
+  }
+
+  #[test]
+  fn test_downsample_old_snapshots() {
+    let start = Local::now();
+    let mut history = History::new(Duration::from_secs(3600));
+    let mut stats = stats(0.0);
+    for i in 0..=1200 {
+      stats.iter_mut().for_each(|stat| stat.req_tot = Some(i as f64));
+      history.record(start + chrono::Duration::seconds(i), &stats);
+    }
+
+    // The last 5 minutes every second, the 15 minutes before at one per minute
+    assert_eq!(history.len(), 301 + 15);
+    let times: Vec<i64> = history.snapshots().take(3).map(|snapshot| (snapshot.time - start).num_seconds()).collect();
+    assert_eq!(times, vec![0, 60, 120]);
+    let app1 = StatKey { resource_type: 2, iid: 4, sid: 1 };
+    let latest: Vec<Option<f64>> =
+      history.series(app1, Duration::from_secs(1)).iter().map(|(_, sample)| sample.get("req_tot")).collect();
+    assert_eq!(latest, vec![Some(1199.0), Some(1200.0)]);
+  }
+
+  #[test]
+  fn test_snapshot_cap() {
+    let start = Local::now();
+    let mut history = History::new(Duration::from_secs(86400 * 7));
+    let stats = stats(0.0);
+    for i in 0..(MAX_SNAPSHOTS as i64 + 100) {
+      history.record(start + chrono::Duration::minutes(i), &stats);
+    }
+    assert_eq!(history.len(), MAX_SNAPSHOTS);
+    assert_eq!(history.snapshots().next().unwrap().time, start + chrono::Duration::minutes(100));
+  }
+
+  #[test]
+  fn test_clones_share_snapshots() {
+    let mut history = History::default();
+    history.record(Local::now(), &stats(1.0));
+    let snapshot = history.clone();
+
+    history.record(Local::now(), &stats(2.0));
+    assert_eq!(snapshot.len(), 1);
+    assert_eq!(history.len(), 2);
+    assert!(Arc::ptr_eq(&snapshot.snapshots[0], &history.snapshots[0]));
+  }
+
+  #[test]
+  fn test_keys_distinguish_frontend_and_backend() {
+    let mut history = History::default();
+    history.record(Local::now(), &stats(1.0));
+
+    let keys: Vec<StatKey> = history.snapshots().next().unwrap().samples.keys().copied().collect();
+    assert_eq!(keys.len(), 5);
+  }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+use super::data::HaproxyStat;
+
+/// How many times slower than its backend's average a server has to respond to count as an outlier.
+pub const OUTLIER_FACTOR: f64 = 2.0;
+
+/// Servers responding within this many milliseconds of the backend average are never outliers, so that
+/// e.g. 3 ms against 1 ms doesn't get flagged.
+pub const OUTLIER_MIN_DIFFERENCE: i64 = 20;
+
+/// Where requests spend their time, averaged over the last 1024 requests, and the maxima, in milliseconds.
+/// HAProxy only reports these for backends and servers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Latency {
+  pub qtime: Option<i64>,
+  pub ctime: Option<i64>,
+  pub rtime: Option<i64>,
+  pub ttime: Option<i64>,
+  pub qtime_max: Option<i64>,
+  pub ctime_max: Option<i64>,
+  pub rtime_max: Option<i64>,
+  pub ttime_max: Option<i64>,
+}
+
+impl Latency {
+  pub fn of(stat: &HaproxyStat) -> Self {
+    Latency {
+      qtime: stat.qtime,
+      ctime: stat.ctime,
+      rtime: stat.rtime,
+      ttime: stat.ttime,
+      qtime_max: stat.qtime_max,
+      ctime_max: stat.ctime_max,
+      rtime_max: stat.rtime_max,
+      ttime_max: stat.ttime_max,
+    }
+  }
+
+  /// Time left over after queueing, connecting and waiting for the response, mostly spent transferring data.
+  pub fn transfer(&self) -> Option<i64> {
+    let waiting = [self.qtime, self.ctime, self.rtime].iter().flatten().sum::<i64>();
+    Some((self.ttime? - waiting).max(0))
+  }
+
+  /// Whether a server with this latency responds well above the average of `backend`.
+  pub fn is_outlier(&self, backend: &Latency) -> bool {
+    match (self.rtime, backend.rtime) {
+      (Some(server), Some(backend)) => {
+        server - backend >= OUTLIER_MIN_DIFFERENCE && server as f64 > backend as f64 * OUTLIER_FACTOR
+      },
+      _ => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn latency(rtime: i64) -> Latency {
+    Latency { qtime: Some(0), ctime: Some(2), rtime: Some(rtime), ttime: Some(rtime + 10), ..Default::default() }
+  }
+
+  #[test]
+  fn test_transfer() {
+    assert_eq!(latency(30).transfer(), Some(8));
+    assert_eq!(Latency::default().transfer(), None);
+    // Averages are taken separately, so they don't always add up
+    assert_eq!(Latency { rtime: Some(50), ttime: Some(40), ..Default::default() }.transfer(), Some(0));
+  }
+
+  #[test]
+  fn test_is_outlier() {
+    let backend = latency(40);
+    assert!(latency(200).is_outlier(&backend));
+    assert!(!latency(70).is_outlier(&backend));
+    assert!(!latency(3).is_outlier(&latency(1)));
+    assert!(!Latency::default().is_outlier(&backend));
+  }
+}
@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use strum::Display;
+
+use super::{data::HaproxyStat, metrics::COUNTER_FIELDS};
+
+/// Counters and gauges that add up across workers.
+const SUMMED_FIELDS: [&str; 46] = [
+  "qcur", "scur", "stot", "bin", "bout", "dreq", "dresp", "ereq", "econ", "eresp", "wretr", "wredis", "chkfail",
+  "lbtot", "rate", "hrsp_1xx", "hrsp_2xx", "hrsp_3xx", "hrsp_4xx", "hrsp_5xx", "hrsp_other", "req_rate", "req_tot",
+  "cli_abrt", "srv_abrt", "comp_in", "comp_out", "comp_byp", "comp_rsp", "conn_rate", "conn_tot", "intercepted",
+  "dcon", "dses", "wrew", "connect", "reuse", "cache_lookups", "cache_hits", "eint", "srv_icur", "idle_conn_cur",
+  "safe_conn_cur", "used_conn_cur", "need_conn_est", "hanafail",
+];
+
+/// Peaks, where the cluster-wide value is the highest any worker has seen.
+const MAX_FIELDS: [&str; 9] =
+  ["qmax", "smax", "rate_max", "req_rate_max", "conn_rate_max", "qtime_max", "ctime_max", "rtime_max", "ttime_max"];
+
+#[derive(Debug, Display, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ProcessKind {
+  Master,
+  Worker,
+  Program,
+}
+
+/// A process listed by `show proc` on the master CLI.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HaproxyProcess {
+  pub pid: u32,
+  pub kind: ProcessKind,
+  pub reloads: Option<u32>,
+  pub uptime: String,
+  pub version: String,
+  /// Left over from a previous reload and still draining connections.
+  pub old: bool,
+  /// Current sessions across all of the worker's frontends.
+  pub sessions: Option<i64>,
+}
+
+/// Which worker's stats to show when connected to a master CLI.
+#[derive(Default, Debug, Display, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WorkerSelection {
+  #[default]
+  All,
+  Pid(u32),
+}
+
+/// What a master CLI reported alongside the stats.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MasterInfo {
+  pub processes: Vec<HaproxyProcess>,
+  pub worker: WorkerSelection,
+  /// Workers that didn't answer `show stat` on the last refresh, usually old ones that exited during a reload.
+  pub skipped: Vec<u32>,
+}
+
+/// Parses `show proc` output from both the 2.0-2.4 format (with a relative PID column) and the 2.5+ one.
+pub fn parse_show_proc(resp: &str) -> Vec<HaproxyProcess> {
+  let mut processes = Vec::new();
+  let mut old = false;
+
+  for line in resp.lines() {
+    let line = line.trim();
+    if line.is_empty() {
+      continue;
+    }
+    if let Some(section) = line.strip_prefix('#') {
+      old = section.trim() == "old workers";
+      continue;
+    }
+
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() < 4 {
+      continue;
+    }
+
+    let pid = match tokens[0].parse() {
+      Ok(pid) => pid,
+      Err(_) => continue,
+    };
+    let kind = match tokens[1] {
+      "master" => ProcessKind::Master,
+      "worker" => ProcessKind::Worker,
+      _ => ProcessKind::Program,
+    };
+
+    // Between the type and the uptime are the reloads, possibly a relative PID and bracketed notes such as
+    // "[was: 1]" or "[failed: 0]". The reload count is the last plain number.
+    let mut bracketed = false;
+    let mut reloads = None;
+    for token in &tokens[2..tokens.len() - 2] {
+      if token.starts_with('[') {
+        bracketed = true;
+      }
+      if !bracketed {
+        reloads = token.parse().ok().or(reloads);
+      }
+      if token.ends_with(']') {
+        bracketed = false;
+      }
+    }
+
+    processes.push(HaproxyProcess {
+      pid,
+      kind,
+      reloads,
+      uptime: tokens[tokens.len() - 2].to_string(),
+      version: tokens[tokens.len() - 1].to_string(),
+      old,
+      sessions: None,
+    });
+  }
+
+  processes
+}
+
+/// Current sessions over all frontends of a worker.
+pub fn frontend_sessions(stats: &[HaproxyStat]) -> i64 {
+  stats.iter().filter(|stat| stat.resource_type == Some(0)).filter_map(|stat| stat.scur).sum()
+}
+
+/// Merges the stats of several workers into one set of rows, matched on proxy and server name. Counters are
+/// added up, peaks take the maximum and everything else is taken from the first worker that reports the row.
+pub fn sum_stats(workers: Vec<Vec<HaproxyStat>>) -> serde_json::Result<Vec<HaproxyStat>> {
+  let mut rows: Vec<Map<String, Value>> = Vec::new();
+  let mut index = std::collections::HashMap::new();
+
+  for stats in workers {
+    for stat in stats {
+      let key = (stat.pxname.clone(), stat.svname.clone());
+      let row = match serde_json::to_value(stat)? {
+        Value::Object(row) => row,
+        _ => continue,
+      };
+
+      let existing = match index.get(&key) {
+        Some(i) => &mut rows[*i],
+        None => {
+          index.insert(key, rows.len());
+          rows.push(row);
+          continue;
+        },
+      };
+
+      for field in SUMMED_FIELDS {
+        combine(existing, &row, field, |a, b| a + b);
+      }
+      for field in MAX_FIELDS {
+        combine(existing, &row, field, f64::max);
+      }
+    }
+  }
+
+  rows.into_iter().map(|row| serde_json::from_value(Value::Object(row))).collect()
+}
+
+/// Adds the counters of workers that are no longer summed, because they exited after a reload or didn't answer,
+/// to the matching rows of `stats`. Without this the totals drop whenever a worker goes away, which reads as a
+/// counter reset. Gauges and peaks only reflect the workers that answered.
+pub fn carry_counters(stats: Vec<HaproxyStat>, gone: &[HaproxyStat]) -> serde_json::Result<Vec<HaproxyStat>> {
+  if gone.is_empty() {
+    return Ok(stats);
+  }
+  let mut carried = std::collections::HashMap::new();
+  for stat in gone {
+    if let Value::Object(row) = serde_json::to_value(stat)? {
+      carried.insert((stat.pxname.clone(), stat.svname.clone()), row);
+    }
+  }
+
+  stats
+    .into_iter()
+    .map(|stat| {
+      let gone = match carried.get(&(stat.pxname.clone(), stat.svname.clone())) {
+        Some(gone) => gone,
+        None => return Ok(stat),
+      };
+      let mut row = match serde_json::to_value(&stat)? {
+        Value::Object(row) => row,
+        _ => return Ok(stat),
+      };
+      for field in SUMMED_FIELDS.iter().filter(|field| COUNTER_FIELDS.contains(field)) {
+        combine(&mut row, gone, field, |a, b| a + b);
+      }
+      serde_json::from_value(Value::Object(row))
+    })
+    .collect()
+}
+
+fn combine(existing: &mut Map<String, Value>, row: &Map<String, Value>, field: &str, op: fn(f64, f64) -> f64) {
+  let value = match (existing.get(field), row.get(field)) {
+    (Some(Value::Number(a)), Some(Value::Number(b))) => match (a.as_i64(), b.as_i64()) {
+      (Some(a), Some(b)) => Value::from(op(a as f64, b as f64) as i64),
+      _ => Value::from(op(a.as_f64().unwrap_or_default(), b.as_f64().unwrap_or_default())),
+    },
+    (Some(Value::Null) | None, Some(b)) => b.clone(),
+    _ => return,
+  };
+  existing.insert(field.to_string(), value);
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_parse_show_proc() {
+    let resp = "#<PID>          <type>          <reloads>       <uptime>        <version>
+1162            master          5 [failed: 0]   0d00h02m07s     2.8.3
+# workers
+1271            worker          1               0d00h00m00s     2.8.3
+# old workers
+1233            worker          3               0d00h00m43s     2.8.3
+# programs
+1244            foo             0               0d00h00m00s     -
+";
+    let processes = parse_show_proc(resp);
+    assert_eq!(processes.len(), 4);
+    assert_eq!(processes[0].kind, ProcessKind::Master);
+    assert_eq!(processes[0].reloads, Some(5));
+    assert_eq!(processes[1].pid, 1271);
+    assert!(!processes[1].old);
+    assert_eq!(processes[2].pid, 1233);
+    assert_eq!(processes[2].reloads, Some(3));
+    assert!(processes[2].old);
+    assert_eq!(processes[3].kind, ProcessKind::Program);
+    assert!(!processes[3].old);
+  }
+
+  #[test]
+  fn test_parse_show_proc_relative_pid() {
+    let resp = "#<PID>          <type>          <relative PID>  <reloads>       <uptime>        <version>
+1162            master          0               5               0d00h02m07s     2.4.22
+# workers
+1271            worker          1               0               0d00h00m00s     2.4.22
+# old workers
+1233            worker          [was: 1]        3               0d00h00m43s     2.4.22
+";
+    let processes = parse_show_proc(resp);
+    assert_eq!(processes.len(), 3);
+    assert_eq!(processes[0].reloads, Some(5));
+    assert_eq!(processes[1].reloads, Some(0));
+    assert_eq!(processes[2].reloads, Some(3));
+    assert_eq!(processes[2].version, "2.4.22");
+  }
+
+  #[test]
+  fn test_sum_stats() {
+    let stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    let summed = sum_stats(vec![stats.clone(), stats.clone()]).unwrap();
+
+    assert_eq!(summed.len(), stats.len());
+    let web = summed.iter().find(|stat| stat.pxname.as_deref() == Some("web")).unwrap();
+    assert_eq!(web.scur, Some(6));
+    assert_eq!(web.smax, Some(15));
+    assert_eq!(web.req_tot, Some(3040.0));
+    assert_eq!(web.slim, Some(262120));
+    assert_eq!(web.status, Some("OPEN".to_string()));
+    assert_eq!(frontend_sessions(&summed), 8);
+  }
+}
@@ -1,3 +1,5 @@
+use std::collections::{BTreeSet, HashMap};
+
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
@@ -6,7 +8,22 @@ use color_eyre::eyre::Result;
 use strum::Display;
 use thiserror::Error;
 
-use super::data::{HaproxyStat, ResourceType};
+use super::{
+  data::{HaproxyStat, ResourceType},
+  history::{History, StatKey},
+  latency::Latency,
+  master::MasterInfo,
+  rates::Rates,
+  typed::FieldMetadata,
+};
+
+/// Fields that only ever grow, for sources that don't report field metadata.
+pub(crate) const COUNTER_FIELDS: [&str; 38] = [
+  "stot", "bin", "bout", "dreq", "dresp", "ereq", "econ", "eresp", "wretr", "wredis", "chkfail", "chkdown", "downtime",
+  "lbtot", "hrsp_1xx", "hrsp_2xx", "hrsp_3xx", "hrsp_4xx", "hrsp_5xx", "hrsp_other", "hanafail", "req_tot",
+  "cli_abrt", "srv_abrt", "comp_in", "comp_out", "comp_byp", "comp_rsp", "conn_tot", "intercepted", "dcon", "dses",
+  "wrew", "connect", "reuse", "cache_lookups", "cache_hits", "eint",
+];
 
 #[derive(Error, Debug)]
 pub enum MetricError {
@@ -19,6 +36,12 @@ pub enum MetricError {
 pub enum HaproxyFrontendStatus {
   Open,
   Closed,
+  /// At its `maxconn`, on older HAProxy versions.
+  Full,
+  /// Disabled with `disable frontend` on HAProxy 2.5+.
+  Paused,
+  /// Disabled or shut down.
+  Stop,
 }
 
 #[derive(Debug, PartialEq)]
@@ -62,9 +85,17 @@ pub struct HaproxyFrontend {
   pub requests: f64,
   #[serde(rename = "scur")]
   pub sessions: i64,
+  #[serde(default)]
+  pub iid: Option<i64>,
 }
 impl FromHaproxyStat for HaproxyFrontend {}
 
+impl HaproxyFrontend {
+  pub fn key(&self) -> StatKey {
+    StatKey { resource_type: 0, iid: self.iid.unwrap_or_default(), sid: 0 }
+  }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct HaproxyBackend {
   #[serde(rename = "pxname")]
@@ -85,6 +116,10 @@ pub struct HaproxyBackend {
   pub http_500_req: Option<f64>,
   #[serde(rename = "hrsp_4xx")]
   pub http_400_req: Option<f64>,
+  #[serde(default)]
+  pub iid: Option<i64>,
+  #[serde(flatten)]
+  pub latency: Latency,
 
   #[serde(skip)]
   pub servers: Vec<HaproxyServer>,
@@ -92,6 +127,12 @@ pub struct HaproxyBackend {
 
 impl FromHaproxyStat for HaproxyBackend {}
 
+impl HaproxyBackend {
+  pub fn key(&self) -> StatKey {
+    StatKey { resource_type: 1, iid: self.iid.unwrap_or_default(), sid: 0 }
+  }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct HaproxyServer {
   #[serde(rename = "svname")]
@@ -104,10 +145,22 @@ pub struct HaproxyServer {
   #[serde(rename = "req_tot")]
   #[serde(deserialize_with = "deserialize_null_default")]
   pub requests: f64,
+  #[serde(default)]
+  pub iid: Option<i64>,
+  #[serde(default)]
+  pub sid: Option<i64>,
+  #[serde(flatten)]
+  pub latency: Latency,
 }
 
 impl FromHaproxyStat for HaproxyServer {}
 
+impl HaproxyServer {
+  pub fn key(&self) -> StatKey {
+    StatKey { resource_type: 2, iid: self.iid.unwrap_or_default(), sid: self.sid.unwrap_or_default() }
+  }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct InstantHaproxyMetricData {
   pub raw: Vec<HaproxyStat>,
@@ -124,6 +177,16 @@ pub struct InstantHaproxyMetrics {
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Default)]
 pub struct HaproxyMetrics {
   pub instant: Option<InstantHaproxyMetrics>,
+  pub master: Option<MasterInfo>,
+  /// Field metadata from `show stat json`/`typed`, empty when reading CSV.
+  #[serde(default)]
+  pub fields: FieldMetadata,
+  /// Snapshots leading up to and including `instant`, filled in by the collector.
+  #[serde(skip)]
+  pub history: History,
+  /// Per-second rates between the last two snapshots in `history`.
+  #[serde(skip)]
+  pub rates: HashMap<StatKey, Rates>,
 }
 
 impl HaproxyMetrics {
@@ -131,6 +194,30 @@ impl HaproxyMetrics {
     Self::default()
   }
 
+  /// Attaches the history this snapshot belongs to and derives rates from it.
+  pub fn set_history(&mut self, history: History) {
+    self.rates = history.rates(|field| self.is_counter(field));
+    self.history = history;
+  }
+
+  /// Names of the columns HAProxy reported that `HaproxyStat` has no field for, see `HaproxyStat::extra`.
+  pub fn extra_fields(&self) -> BTreeSet<String> {
+    let raw = self.instant.as_ref().map(|instant| instant.data.raw.as_slice()).unwrap_or_default();
+    raw.iter().flat_map(|stat| stat.extra.keys().cloned()).collect()
+  }
+
+  pub fn rates(&self, key: StatKey) -> Rates {
+    self.rates.get(&key).copied().unwrap_or_default()
+  }
+
+  /// Whether a field is a counter, which is only meaningful as a rate. Uses HAProxy's metadata when available.
+  pub fn is_counter(&self, field: &str) -> bool {
+    match self.fields.get(field) {
+      Some(meta) => meta.needs_rate(),
+      None => COUNTER_FIELDS.contains(&field),
+    }
+  }
+
   pub fn update(&mut self, data: Vec<HaproxyStat>) -> Result<()> {
     let mut frontends: Vec<HaproxyFrontend> = Vec::new();
     let mut backends: Vec<HaproxyBackend> = Vec::new();
@@ -175,3 +262,42 @@ where
   let opt = Option::deserialize(deserializer)?;
   Ok(opt.unwrap_or_default())
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::stats::typed::parse_typed;
+
+  #[test]
+  fn test_is_counter() {
+    let mut metrics = HaproxyMetrics::new();
+    assert!(metrics.is_counter("stot"));
+    assert!(!metrics.is_counter("scur"));
+
+    // HAProxy's own metadata wins, including for fields the fallback list doesn't know
+    let typed = parse_typed("F.3.0.7.stot.1:MGP:u64:12\nF.3.0.104.h1sess.1:MCP:u64:12\n").unwrap();
+    metrics.fields = typed.fields;
+    assert!(!metrics.is_counter("stot"));
+    assert!(metrics.is_counter("h1sess"));
+  }
+
+  #[test]
+  fn test_latency() {
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap()).unwrap();
+    let backend = &metrics.instant.unwrap().data.backends[0];
+
+    assert_eq!(backend.latency.rtime, Some(15));
+    assert_eq!(backend.latency.ttime_max, Some(340));
+    assert_eq!(backend.servers[0].latency.rtime, Some(12));
+    assert_eq!(backend.servers[1].latency.rtime_max, Some(320));
+  }
+
+  #[test]
+  fn test_stopped_frontend() {
+    let csv = include_str!("../../tests/fixtures/show_stat.csv").replacen(",OPEN,", ",STOP,", 1);
+    let mut metrics = HaproxyMetrics::new();
+    metrics.update(HaproxyStat::parse_csv(&csv).unwrap()).unwrap();
+    assert_eq!(metrics.instant.unwrap().data.frontends[0].status, HaproxyFrontendStatus::Stop);
+  }
+}
@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+
+use super::history::{History, Sample, StatKey};
+
+/// Per-second rates of a frontend, backend or server between the two most recent snapshots.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rates {
+  /// HTTP requests, or sessions for proxies that don't count requests (TCP mode, servers on older versions).
+  pub requests: Option<f64>,
+  pub bytes_in: Option<f64>,
+  pub bytes_out: Option<f64>,
+  /// Request, connection and response errors combined.
+  pub errors: Option<f64>,
+  pub http_4xx: Option<f64>,
+  pub http_5xx: Option<f64>,
+  /// HTTP responses of any status.
+  pub http_responses: Option<f64>,
+}
+
+/// Change per second of a counter. A counter that went down was reset by a reload or `clear counters`, so
+/// everything it counted since then is new.
+fn per_second(previous: &Sample, current: &Sample, field: &str, seconds: f64) -> Option<f64> {
+  let (old, new) = (previous.get(field)?, current.get(field)?);
+  let delta = if new >= old { new - old } else { new };
+  Some(delta / seconds)
+}
+
+fn sum(values: impl IntoIterator<Item = Option<f64>>) -> Option<f64> {
+  values.into_iter().flatten().fold(None, |total, value| Some(total.unwrap_or(0.0) + value))
+}
+
+impl Rates {
+  /// Only fields `is_counter` accepts get a rate, so that HAProxy's field metadata has the last word.
+  fn between(previous: &Sample, current: &Sample, seconds: f64, is_counter: &impl Fn(&str) -> bool) -> Rates {
+    let rate = |field| if is_counter(field) { per_second(previous, current, field, seconds) } else { None };
+    Rates {
+      requests: rate("req_tot").or_else(|| rate("stot")),
+      bytes_in: rate("bin"),
+      bytes_out: rate("bout"),
+      errors: sum(["ereq", "econ", "eresp"].map(rate)),
+      http_4xx: rate("hrsp_4xx"),
+      http_5xx: rate("hrsp_5xx"),
+      http_responses: sum(["hrsp_1xx", "hrsp_2xx", "hrsp_3xx", "hrsp_4xx", "hrsp_5xx", "hrsp_other"].map(rate)),
+    }
+  }
+
+  /// Share of responses since the previous refresh that were 4xx, in percent.
+  pub fn ratio_4xx(&self) -> Option<f64> {
+    percentage(self.http_4xx, self.http_responses)
+  }
+
+  /// Share of responses since the previous refresh that were 5xx, in percent.
+  pub fn ratio_5xx(&self) -> Option<f64> {
+    percentage(self.http_5xx, self.http_responses)
+  }
+}
+
+/// `None` without any responses to compare against, rather than a misleading 0%.
+fn percentage(part: Option<f64>, total: Option<f64>) -> Option<f64> {
+  match (part, total) {
+    (Some(part), Some(total)) if total > 0.0 => Some(part / total * 100.0),
+    _ => None,
+  }
+}
+
+impl History {
+  /// Rates for everything in the latest snapshot that was also in the one before it, of the fields that are
+  /// counters according to `is_counter`.
+  pub fn rates(&self, is_counter: impl Fn(&str) -> bool) -> HashMap<StatKey, Rates> {
+    let mut snapshots = self.snapshots().rev();
+    let (current, previous) = match (snapshots.next(), snapshots.next()) {
+      (Some(current), Some(previous)) => (current, previous),
+      _ => return HashMap::new(),
+    };
+
+    let seconds = (current.time - previous.time).num_milliseconds() as f64 / 1000.0;
+    if seconds <= 0.0 {
+      return HashMap::new();
+    }
+
+    current
+      .samples
+      .iter()
+      .filter_map(|(key, sample)| {
+        previous.samples.get(key).map(|previous| (*key, Rates::between(previous, sample, seconds, &is_counter)))
+      })
+      .collect()
+  }
+
+  /// Per-second rate of one counter between consecutive snapshots in the last `window`, oldest first.
+  pub fn rate_series(&self, key: StatKey, field: &str, window: Duration) -> Vec<(DateTime<Local>, f64)> {
+    self
+      .series(key, window)
+      .windows(2)
+      .filter_map(|pair| {
+        let ((previous_time, previous), (time, current)) = (pair[0], pair[1]);
+        let seconds = (time - previous_time).num_milliseconds() as f64 / 1000.0;
+        if seconds <= 0.0 {
+          return None;
+        }
+        per_second(previous, current, field, seconds).map(|rate| (time, rate))
+      })
+      .collect()
+  }
+}
+
+/// Formats a rate compactly for table cells, e.g. `12.5/s` or `3.4k/s`.
+pub fn format_rate(rate: Option<f64>) -> String {
+  match rate {
+    None => "-".to_string(),
+    Some(rate) if rate >= 1_000_000.0 => format!("{:.1}M/s", rate / 1_000_000.0),
+    Some(rate) if rate >= 1_000.0 => format!("{:.1}k/s", rate / 1_000.0),
+    Some(rate) if rate >= 10.0 || rate == 0.0 => format!("{:.0}/s", rate),
+    Some(rate) => format!("{:.1}/s", rate),
+  }
+}
+
+/// Formats a percentage such as an error ratio, e.g. `2.5%`.
+pub fn format_percent(percent: Option<f64>) -> String {
+  match percent {
+    Some(percent) => format!("{:.1}%", percent),
+    None => "-".to_string(),
+  }
+}
+
+/// Formats a number of bytes with binary units, e.g. `1.2 MiB`.
+pub fn format_bytes(bytes: Option<f64>) -> String {
+  let mut bytes = match bytes {
+    Some(bytes) => bytes,
+    None => return "-".to_string(),
+  };
+  for unit in ["B", "KiB", "MiB", "GiB"] {
+    if bytes < 1024.0 {
+      return format!("{:.1} {}", bytes, unit);
+    }
+    bytes /= 1024.0;
+  }
+  format!("{:.1} TiB", bytes)
+}
+
+/// Formats a byte rate with binary units, e.g. `1.2 MiB/s`.
+pub fn format_bytes_rate(rate: Option<f64>) -> String {
+  match rate {
+    Some(_) => format!("{}/s", format_bytes(rate)),
+    None => "-".to_string(),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::Local;
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+  use crate::stats::{data::HaproxyStat, metrics::COUNTER_FIELDS};
+
+  const APP: StatKey = StatKey { resource_type: 1, iid: 4, sid: 0 };
+
+  fn counter(field: &str) -> bool {
+    COUNTER_FIELDS.contains(&field)
+  }
+
+  fn stats(req_tot: f64, bin: i64, ereq: i64, econ: i64) -> Vec<HaproxyStat> {
+    let mut stats = HaproxyStat::parse_csv(include_str!("../../tests/fixtures/show_stat.csv")).unwrap();
+    for stat in &mut stats {
+      stat.req_tot = Some(req_tot);
+      stat.bin = Some(bin);
+      stat.ereq = Some(ereq);
+      stat.econ = Some(econ);
+      stat.eresp = None;
+    }
+    stats
+  }
+
+  #[test]
+  fn test_rates() {
+    let start = Local::now();
+    let mut history = History::default();
+    history.record(start, &stats(100.0, 1000, 1, 1));
+    assert!(history.rates(counter).is_empty());
+
+    history.record(start + chrono::Duration::seconds(10), &stats(150.0, 3048, 3, 4));
+    let rates = history.rates(counter)[&APP];
+    assert_eq!(rates.requests, Some(5.0));
+    assert_eq!(rates.bytes_in, Some(204.8));
+    assert_eq!(rates.errors, Some(0.5));
+
+    // Fields the metadata doesn't call counters get no rate
+    let rates = history.rates(|field| field != "bin")[&APP];
+    assert_eq!(rates.requests, Some(5.0));
+    assert_eq!(rates.bytes_in, None);
+  }
+
+  #[test]
+  fn test_rates_after_reset() {
+    let start = Local::now();
+    let mut history = History::default();
+    history.record(start, &stats(5000.0, 1000, 10, 10));
+    // Counters were cleared and have counted 20 requests since
+    history.record(start + chrono::Duration::seconds(10), &stats(20.0, 1000, 10, 10));
+
+    let rates = history.rates(counter)[&APP];
+    assert_eq!(rates.requests, Some(2.0));
+    assert_eq!(rates.bytes_in, Some(0.0));
+    assert_eq!(rates.errors, Some(0.0));
+  }
+
+  #[test]
+  fn test_requests_fall_back_to_sessions() {
+    let start = Local::now();
+    let mut history = History::default();
+    let mut first = stats(0.0, 0, 0, 0);
+    let mut second = stats(0.0, 0, 0, 0);
+    for stat in first.iter_mut().chain(second.iter_mut()) {
+      stat.req_tot = None;
+    }
+    first[4].stot = Some(100);
+    second[4].stot = Some(130);
+    history.record(start, &first);
+    history.record(start + chrono::Duration::seconds(10), &second);
+
+    assert_eq!(history.rates(counter)[&APP].requests, Some(3.0));
+  }
+
+  #[test]
+  fn test_rate_series() {
+    let start = Local::now();
+    let mut history = History::default();
+    for (i, req_tot) in [100.0, 150.0, 250.0, 10.0].iter().enumerate() {
+      history.record(start + chrono::Duration::seconds(i as i64 * 10), &stats(*req_tot, 0, 0, 0));
+    }
+
+    let series = history.rate_series(APP, "req_tot", Duration::from_secs(3600));
+    let rates: Vec<f64> = series.iter().map(|(_, rate)| *rate).collect();
+    assert_eq!(rates, vec![5.0, 10.0, 1.0]);
+    assert_eq!(series[0].0, start + chrono::Duration::seconds(10));
+    assert!(history.rate_series(APP, "pxname", Duration::from_secs(3600)).is_empty());
+  }
+
+  #[test]
+  fn test_error_ratios() {
+    let start = Local::now();
+    let mut history = History::default();
+    let mut first = stats(0.0, 0, 0, 0);
+    let mut second = stats(0.0, 0, 0, 0);
+    for (stats, (ok, client, server)) in [(&mut first, (100.0, 10.0, 5.0)), (&mut second, (170.0, 30.0, 15.0))] {
+      for stat in stats.iter_mut() {
+        (stat.hrsp_1xx, stat.hrsp_3xx, stat.hrsp_other) = (Some(0.0), None, Some(0.0));
+        (stat.hrsp_2xx, stat.hrsp_4xx, stat.hrsp_5xx) = (Some(ok), Some(client), Some(server));
+      }
+    }
+    history.record(start, &first);
+    history.record(start + chrono::Duration::seconds(10), &second);
+
+    // 70 2xx, 20 4xx and 10 5xx in between
+    let rates = history.rates(counter)[&APP];
+    assert_eq!(rates.ratio_4xx(), Some(20.0));
+    assert_eq!(rates.ratio_5xx(), Some(10.0));
+    assert_eq!(Rates::default().ratio_5xx(), None);
+  }
+
+  #[test]
+  fn test_format() {
+    assert_eq!(format_rate(None), "-");
+    assert_eq!(format_rate(Some(0.0)), "0/s");
+    assert_eq!(format_rate(Some(0.25)), "0.2/s");
+    assert_eq!(format_rate(Some(42.4)), "42/s");
+    assert_eq!(format_rate(Some(4200.0)), "4.2k/s");
+    assert_eq!(format_percent(Some(2.54)), "2.5%");
+    assert_eq!(format_percent(None), "-");
+    assert_eq!(format_bytes_rate(Some(512.0)), "512.0 B/s");
+    assert_eq!(format_bytes_rate(Some(1536.0 * 1024.0)), "1.5 MiB/s");
+    assert_eq!(format_bytes_rate(None), "-");
+    assert_eq!(format_bytes(Some(3.0 * 1024.0 * 1024.0 * 1024.0)), "3.0 GiB");
+  }
+}
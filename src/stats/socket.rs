@@ -1,78 +1,675 @@
+use std::collections::HashMap;
 use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
 use std::os::unix::net::UnixStream;
-use std::time::Duration;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
-use color_eyre::eyre::Result;
-use tokio::sync::mpsc::UnboundedSender;
+use color_eyre::eyre::{eyre, Result};
+use lazy_static::lazy_static;
+use thiserror::Error;
 
-use super::{data::HaproxyStat, metrics::HaproxyMetrics};
-use crate::action::Action;
+use super::{
+  admin::{parse_response, WorkerResponse},
+  collector::{Collector, CollectorCommand, CONNECT_TIMEOUT, IO_TIMEOUT},
+  data::HaproxyStat,
+  master::{
+    carry_counters, frontend_sessions, parse_show_proc, sum_stats, HaproxyProcess, MasterInfo, ProcessKind,
+    WorkerSelection,
+  },
+  typed::{parse_descriptions, parse_json, parse_typed, FieldMetadata, StatFormat},
+};
+
+#[derive(Error, Debug)]
+pub enum SocketError {
+  #[error("Invalid socket address: {0}")]
+  InvalidAddress(String),
+}
+
+/// Where the HAProxy stats socket lives. Accepts `unix:/path`, `tcp:host:port` or a plain path.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SocketAddress {
+  Unix(String),
+  Tcp(String),
+}
+
+impl FromStr for SocketAddress {
+  type Err = SocketError;
+
+  fn from_str(s: &str) -> Result<Self, SocketError> {
+    let s = s.trim();
+    match s.split_once(':') {
+      Some(("unix", path)) if !path.is_empty() => Ok(SocketAddress::Unix(path.to_string())),
+      Some(("tcp", addr)) => match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+          Ok(SocketAddress::Tcp(addr.to_string()))
+        },
+        _ => Err(SocketError::InvalidAddress(s.to_string())),
+      },
+      _ if !s.is_empty() => Ok(SocketAddress::Unix(s.to_string())),
+      _ => Err(SocketError::InvalidAddress(s.to_string())),
+    }
+  }
+}
+
+impl SocketAddress {
+  /// Whether HAProxy runs on this machine, so that the PID it reports can be looked up in `/proc`.
+  pub fn is_local(&self) -> bool {
+    match self {
+      SocketAddress::Unix(_) => true,
+      SocketAddress::Tcp(addr) => {
+        let host = addr.rsplit_once(':').map_or(addr.as_str(), |(host, _)| host);
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        host == "localhost" || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+      },
+    }
+  }
+}
+
+enum Stream {
+  Unix(UnixStream),
+  Tcp(TcpStream),
+}
+
+impl Stream {
+  fn connect(address: &SocketAddress) -> Result<Stream> {
+    let stream = match address {
+      SocketAddress::Unix(path) => Stream::Unix(UnixStream::connect(path)?),
+      SocketAddress::Tcp(addr) => {
+        let mut last_error = None;
+        let mut stream = None;
+        for addr in addr.to_socket_addrs()? {
+          match TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT) {
+            Ok(connected) => {
+              stream = Some(connected);
+              break;
+            },
+            Err(e) => last_error = Some(e),
+          }
+        }
+        match (stream, last_error) {
+          (Some(stream), _) => Stream::Tcp(stream),
+          (None, Some(e)) => return Err(e.into()),
+          (None, None) => return Err(SocketError::InvalidAddress(addr.clone()).into()),
+        }
+      },
+    };
+
+    // A hung HAProxy would otherwise block the collector (and anyone sharing the connection) forever
+    match &stream {
+      Stream::Unix(stream) => {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+      },
+      Stream::Tcp(stream) => {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+      },
+    }
+
+    Ok(stream)
+  }
+}
+
+impl Read for Stream {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    match self {
+      Stream::Unix(stream) => stream.read(buf),
+      Stream::Tcp(stream) => stream.read(buf),
+    }
+  }
+}
+
+impl Write for Stream {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    match self {
+      Stream::Unix(stream) => stream.write(buf),
+      Stream::Tcp(stream) => stream.write(buf),
+    }
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    match self {
+      Stream::Unix(stream) => stream.flush(),
+      Stream::Tcp(stream) => stream.flush(),
+    }
+  }
+}
+
+lazy_static! {
+  /// One interactive connection per socket, shared by the collector and every component that talks to HAProxy.
+  static ref CONNECTIONS: Mutex<HashMap<SocketAddress, Arc<Mutex<Connection>>>> = Mutex::new(HashMap::new());
+}
+
+/// A long-lived connection in HAProxy's interactive (`prompt`) mode. HAProxy ends every response with an empty
+/// line followed by the prompt, which is how responses are told apart on the stream.
+struct Connection {
+  stream: Option<Stream>,
+}
+
+impl Connection {
+  fn shared(address: &SocketAddress) -> Arc<Mutex<Connection>> {
+    let mut connections = CONNECTIONS.lock().unwrap_or_else(|e| e.into_inner());
+    connections.entry(address.clone()).or_insert_with(|| Arc::new(Mutex::new(Connection { stream: None }))).clone()
+  }
+
+  fn open(address: &SocketAddress) -> Result<Stream> {
+    log::debug!("Connection::open: Connecting to {:?}", address);
+    let mut stream = Stream::connect(address)?;
+
+    stream.write_all(b"prompt\n")?;
+    read_response(&mut stream)?;
+
+    Ok(stream)
+  }
+
+  /// Runs a command on the open connection. With `resend`, a command that fails is sent again once on a new
+  /// connection, in case HAProxy dropped the old one in the meantime. Only safe for read-only commands, as the
+  /// first attempt may have reached HAProxy before e.g. the read timed out.
+  fn query(&mut self, address: &SocketAddress, command: &str, resend: bool) -> Result<String> {
+    let attempts = if resend { 2 } else { 1 };
+    for attempt in 0..attempts {
+      if self.stream.is_none() {
+        self.stream = Some(Connection::open(address)?);
+      }
+      let stream = self.stream.as_mut().unwrap();
+
+      let resp = stream.write_all(format!("{}\n", command).as_bytes()).map_err(Into::into);
+      let resp = resp.and_then(|_| read_response(stream));
+      match resp {
+        Ok(resp) => return Ok(resp),
+        Err(e) if attempt + 1 < attempts => {
+          log::warn!("Connection::query: {:?} failed on {:?}, reconnecting: {}", command, address, e);
+          self.stream = None;
+        },
+        Err(e) => {
+          self.stream = None;
+          return Err(e);
+        },
+      }
+    }
+    unreachable!()
+  }
+}
+
+/// Reads a single response in interactive mode: everything up to the empty line before the next prompt.
+fn read_response(stream: &mut impl Read) -> Result<String> {
+  let mut buf = Vec::new();
+  let mut chunk = [0u8; 8192];
+
+  loop {
+    let n = stream.read(&mut chunk)?;
+    if n == 0 {
+      return Err(eyre!("Connection closed by HAProxy"));
+    }
+    buf.extend_from_slice(&chunk[..n]);
+
+    if let Some(end) = response_end(&buf) {
+      buf.truncate(end);
+      return Ok(String::from_utf8_lossy(&buf).into_owned());
+    }
+  }
+}
+
+/// Finds where the response ends if `buf` finishes with an empty line and a prompt such as `> ` or `master> `.
+fn response_end(buf: &[u8]) -> Option<usize> {
+  if !buf.ends_with(b"> ") {
+    return None;
+  }
+  let prompt = buf.iter().rposition(|b| *b == b'\n')?;
+  match prompt {
+    0 => Some(0),
+    _ if buf[prompt - 1] == b'\n' => Some(prompt),
+    _ => None,
+  }
+}
 
 pub struct Socket {
-  stream_path: String,
+  address: SocketAddress,
+  /// Whether this is a master CLI, unknown until the first refresh.
+  master: Option<bool>,
+  processes: Vec<HaproxyProcess>,
+  /// Workers left out of the last refresh because their `show stat` failed.
+  skipped: Vec<u32>,
+  /// The last stats each worker answered with, so its counters stay in the sum once it stops answering.
+  worker_stats: HashMap<u32, Vec<HaproxyStat>>,
+  /// Counters of workers that have left the process list, summed.
+  departed: Vec<HaproxyStat>,
+  worker: WorkerSelection,
+  format: StatFormat,
+  fields: FieldMetadata,
 }
 
 impl Socket {
-  pub fn new(stream_path: String) -> Result<Socket> {
-    Ok(Socket { stream_path })
+  pub fn new(address: String) -> Result<Socket> {
+    Ok(Socket::from_address(address.parse()?))
+  }
+
+  pub fn from_address(address: SocketAddress) -> Socket {
+    Socket {
+      address,
+      master: None,
+      processes: Vec::new(),
+      skipped: Vec::new(),
+      worker_stats: HashMap::new(),
+      departed: Vec::new(),
+      worker: WorkerSelection::default(),
+      format: StatFormat::default(),
+      fields: FieldMetadata::new(),
+    }
   }
 
-  pub fn refresh(&mut self, action_tx: UnboundedSender<Action>) -> Result<()> {
-    let mut stream = UnixStream::connect(&self.stream_path)?;
+  pub fn with_format(mut self, format: StatFormat) -> Socket {
+    self.format = format;
+    self
+  }
 
-    loop {
-      match stream.write(b"show stat\n") {
-        Ok(_) => {
-          log::debug!("Querying stats");
-          break;
-        },
+  /// Sends a read-only command over the shared interactive connection for this address and returns the response.
+  /// It's sent again if the connection turns out to be broken.
+  pub fn query(&self, command: &str) -> Result<String> {
+    log::debug!("Socket::query: Sending {:?} to {:?}", command, self.address);
+    let connection = Connection::shared(&self.address);
+    let mut connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+    connection.query(&self.address, command, true)
+  }
+
+  /// Sends a command that changes HAProxy's state over the shared connection. Unlike `query`, it's never sent
+  /// twice, as a broken connection may not have stopped HAProxy from applying it the first time.
+  pub fn send(&self, command: &str) -> Result<String> {
+    log::debug!("Socket::send: Sending {:?} to {:?}", command, self.address);
+    let connection = Connection::shared(&self.address);
+    let mut connection = connection.lock().unwrap_or_else(|e| e.into_inner());
+    connection.query(&self.address, command, false)
+  }
+
+  /// Reads the process list from a master CLI. Returns `None` when the socket belongs to a worker, which
+  /// doesn't know `show proc`.
+  fn show_proc(&self) -> Result<Option<Vec<HaproxyProcess>>> {
+    let processes = parse_show_proc(&self.query("show proc")?);
+    if processes.iter().any(|process| process.kind == ProcessKind::Master) {
+      Ok(Some(processes))
+    } else {
+      Ok(None)
+    }
+  }
+
+  /// Runs `show stat` in the configured format, prefixed with e.g. `@!1234 ` to address a worker. HAProxy
+  /// versions without typed output make the socket fall back to CSV for good.
+  fn show_stat(&mut self, prefix: &str) -> Result<Vec<HaproxyStat>> {
+    let command = format!("{}show stat", prefix);
+    let parsed = match self.format {
+      StatFormat::Csv => {
+        let resp = self.query(&command)?;
+        // Errors such as a worker that's gone come back as plain text, which would otherwise parse as no stats
+        if !resp.starts_with("# pxname") {
+          return Err(eyre!("{:?} answered {:?}", command, resp.trim()));
+        }
+        return Ok(HaproxyStat::parse_csv(&resp)?);
+      },
+      StatFormat::Json => parse_json(&self.query(&format!("{} json", command))?),
+      StatFormat::Typed => parse_typed(&self.query(&format!("{} typed", command))?),
+    };
+
+    match parsed {
+      Ok(typed) => {
+        if self.fields.is_empty() {
+          self.fields = typed.fields;
+          self.load_descriptions(prefix);
+        }
+        Ok(typed.stats)
+      },
+      Err(e) => {
+        log::warn!("Socket::show_stat: {:?} doesn't support {} stats, using CSV: {}", self.address, self.format, e);
+        self.format = StatFormat::Csv;
+        self.show_stat(prefix)
+      },
+    }
+  }
+
+  /// Fills in field descriptions from `show stat desc typed` where HAProxy provides one.
+  fn load_descriptions(&mut self, prefix: &str) {
+    let descriptions = match self.query(&format!("{}show stat desc typed", prefix)) {
+      Ok(resp) => parse_descriptions(&resp),
+      Err(e) => {
+        log::debug!("Socket::load_descriptions: No field descriptions from {:?}: {}", self.address, e);
+        return;
+      },
+    };
+    for (name, meta) in self.fields.iter_mut() {
+      if meta.description.is_none() {
+        meta.description = descriptions.get(name).cloned();
+      }
+    }
+  }
+
+  /// Fetches stats through the master CLI, from every worker so old ones still draining are accounted for. A
+  /// worker that fails to answer, e.g. an old one exiting between `show proc` and `show stat`, is skipped.
+  fn fetch_worker_stats(&mut self, mut processes: Vec<HaproxyProcess>) -> Result<Vec<HaproxyStat>> {
+    let mut selected = Vec::new();
+    let mut all = Vec::new();
+    let mut skipped = Vec::new();
+
+    for process in processes.iter_mut().filter(|process| process.kind == ProcessKind::Worker) {
+      let stats = match self.show_stat(&format!("@!{} ", process.pid)) {
+        Ok(stats) => stats,
         Err(e) => {
-          println!("Error: {}", e);
+          log::warn!("Socket::fetch_worker_stats: Skipping worker {} on {:?}: {}", process.pid, self.address, e);
+          skipped.push(process.pid);
+          continue;
         },
+      };
+      process.sessions = Some(frontend_sessions(&stats));
+      self.worker_stats.insert(process.pid, stats.clone());
+
+      match self.worker {
+        WorkerSelection::Pid(pid) if pid == process.pid => selected = stats,
+        WorkerSelection::Pid(_) => {},
+        WorkerSelection::All => all.push(stats),
       }
     }
 
-    loop {
-      let mut resp = String::new();
-      stream.read_to_string(&mut resp)?;
-
-      // Uncomment the following code if you need to write to a file synchronously
-      // let timestamp = Utc::now().format("%Y-%m-%d_%H-%M-%S");
-      // let filename = format!("stats_{}.csv", timestamp);
-      // let mut file = File::create(filename)?;
-      // file.write_all(resp.as_bytes())?;
-
-      let stats = HaproxyStat::parse_csv(&resp)?;
-      
-      // Process metrics in background thread to avoid blocking UI
-      let mut metrics = HaproxyMetrics::new();
-      metrics.update(stats)?;
-      
-      if action_tx.is_closed() {
-        return Ok(());
+    let gone: Vec<u32> = self
+      .worker_stats
+      .keys()
+      .filter(|pid| !processes.iter().any(|process| process.pid == **pid && process.kind == ProcessKind::Worker))
+      .copied()
+      .collect();
+    if !gone.is_empty() {
+      let mut departed = vec![std::mem::take(&mut self.departed)];
+      departed.extend(gone.iter().filter_map(|pid| self.worker_stats.remove(pid)));
+      self.departed = sum_stats(departed)?;
+    }
+
+    self.processes = processes;
+    let answered = self.processes.iter().filter(|process| process.kind == ProcessKind::Worker).count() > skipped.len();
+    self.skipped = skipped;
+    if !answered {
+      return Err(eyre!("No worker behind {:?} answered show stat", self.address));
+    }
+
+    match self.worker {
+      WorkerSelection::All => {
+        let mut gone = vec![self.departed.clone()];
+        gone.extend(self.skipped.iter().filter_map(|pid| self.worker_stats.get(pid).cloned()));
+        Ok(carry_counters(sum_stats(all)?, &sum_stats(gone)?)?)
+      },
+      WorkerSelection::Pid(pid) => {
+        if !self.processes.iter().any(|process| process.pid == pid) || self.skipped.contains(&pid) {
+          log::warn!("Socket::fetch_worker_stats: Worker {} is gone, showing all workers", pid);
+          self.worker = WorkerSelection::All;
+          return self.fetch_worker_stats(self.processes.clone());
+        }
+        Ok(selected)
+      },
+    }
+  }
+}
+
+impl Collector for Socket {
+  fn fetch_stats(&mut self) -> Result<Vec<HaproxyStat>> {
+    if self.master != Some(false) {
+      match self.show_proc()? {
+        Some(processes) => {
+          self.master = Some(true);
+          return self.fetch_worker_stats(processes);
+        },
+        None => self.master = Some(false),
       }
-      
-      // Send processed metrics instead of raw stats
-      action_tx.send(Action::MetricUpdate(Arc::new(metrics)))?;
+    }
 
-      break;
+    self.show_stat("")
+  }
+
+  fn fields(&self) -> FieldMetadata {
+    self.fields.clone()
+  }
+
+  fn master(&self) -> Option<MasterInfo> {
+    match self.master {
+      Some(true) => {
+        Some(MasterInfo { processes: self.processes.clone(), worker: self.worker, skipped: self.skipped.clone() })
+      },
+      _ => None,
     }
+  }
 
-    Ok(())
+  fn handle_command(&mut self, command: CollectorCommand) {
+    if let CollectorCommand::SelectWorker(worker) = command {
+      self.worker = worker;
+    }
+  }
+
+  /// Runs `command` on the socket. Through a master CLI it goes to every current worker instead, as that's
+  /// where e.g. server states live, and each worker's answer is kept apart.
+  fn run(&mut self, command: &str) -> Result<Vec<WorkerResponse>> {
+    if self.master != Some(true) {
+      let response = parse_response(&self.send(command)?);
+      return Ok(vec![WorkerResponse { pid: None, response }]);
+    }
+
+    let workers: Vec<u32> = self
+      .processes
+      .iter()
+      .filter(|process| process.kind == ProcessKind::Worker && !process.old)
+      .map(|process| process.pid)
+      .collect();
+    if workers.is_empty() {
+      return Err(eyre!("No current workers behind {:?} to send {:?} to", self.address, command));
+    }
+
+    let responses = workers.into_iter().map(|pid| {
+      let response = self.send(&format!("@!{} {}", pid, command)).map_err(|e| e.to_string());
+      WorkerResponse { pid: Some(pid), response: response.and_then(|resp| parse_response(&resp)) }
+    });
+    Ok(responses.collect())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  #[test]
+  fn test_parse_plain_path() {
+    let address: SocketAddress = "/var/run/haproxy/admin.sock".parse().unwrap();
+    assert_eq!(address, SocketAddress::Unix("/var/run/haproxy/admin.sock".to_string()));
+  }
+
+  #[test]
+  fn test_parse_unix_prefix() {
+    let address: SocketAddress = "unix:/var/run/haproxy.sock".parse().unwrap();
+    assert_eq!(address, SocketAddress::Unix("/var/run/haproxy.sock".to_string()));
+  }
+
+  #[test]
+  fn test_parse_tcp() {
+    let address: SocketAddress = "tcp:127.0.0.1:9999".parse().unwrap();
+    assert_eq!(address, SocketAddress::Tcp("127.0.0.1:9999".to_string()));
+
+    let address: SocketAddress = "tcp:lb1.example.com:9999".parse().unwrap();
+    assert_eq!(address, SocketAddress::Tcp("lb1.example.com:9999".to_string()));
   }
 
-  pub fn collect(&mut self, action_tx: UnboundedSender<Action>) -> Result<()> {
-    // Initialize prompt mode, waiting until we can write
-    loop {
-      self.refresh(action_tx.clone())?;
-      for _ in 0..10 {
-        // check if channel is closed
-        if action_tx.is_closed() {
-          return Ok(());
+  #[test]
+  fn test_is_local() {
+    let local = |address: &str| address.parse::<SocketAddress>().unwrap().is_local();
+    assert!(local("/var/run/haproxy.sock"));
+    assert!(local("tcp:127.0.0.1:9999"));
+    assert!(local("tcp:localhost:9999"));
+    assert!(local("tcp:[::1]:9999"));
+    assert!(!local("tcp:10.0.0.5:9999"));
+    assert!(!local("tcp:lb1.example.com:9999"));
+  }
+
+  #[test]
+  fn test_parse_invalid() {
+    assert!("tcp:127.0.0.1".parse::<SocketAddress>().is_err());
+    assert!("tcp::9999".parse::<SocketAddress>().is_err());
+    assert!("tcp:host:notaport".parse::<SocketAddress>().is_err());
+    assert!("".parse::<SocketAddress>().is_err());
+  }
+
+  /// Pretends to be HAProxy in interactive mode, answering every command with `<command> ok`. Each accepted
+  /// connection serves `commands` commands before it is closed.
+  fn fake_haproxy(connections: usize, commands: usize) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    fake_haproxy_answering(connections, commands, |command| format!("{} ok\nsecond line\n", command))
+  }
+
+  /// Like `fake_haproxy`, answering with whatever `answer` returns for a command.
+  fn fake_haproxy_answering(
+    connections: usize,
+    commands: usize,
+    answer: fn(&str) -> String,
+  ) -> (String, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, BufReader};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = format!("tcp:{}", listener.local_addr().unwrap());
+
+    let handle = std::thread::spawn(move || {
+      let mut received = Vec::new();
+      for _ in 0..connections {
+        let (mut conn, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(conn.try_clone().unwrap());
+        for i in 0..=commands {
+          let mut line = String::new();
+          reader.read_line(&mut line).unwrap();
+          let line = line.trim().to_string();
+          if i == 0 {
+            assert_eq!(line, "prompt");
+            conn.write_all(b"\n> ").unwrap();
+          } else {
+            conn.write_all(format!("{}\n> ", answer(&line)).as_bytes()).unwrap();
+          }
+          received.push(line);
         }
-        std::thread::sleep(Duration::from_millis(1000));
       }
-    }
+      received
+    });
+
+    (addr, handle)
+  }
+
+  #[test]
+  fn test_response_end() {
+    assert_eq!(response_end(b"\n> "), Some(0));
+    assert_eq!(response_end(b"Pid: 42\n\n> "), Some(8));
+    assert_eq!(response_end(b"Pid: 42\n\nmaster> "), Some(8));
+    assert_eq!(response_end(b"Pid: 42\n"), None);
+    assert_eq!(response_end(b"Pid: 42\n> "), None);
+  }
+
+  #[test]
+  fn test_query_reuses_connection() -> Result<()> {
+    let (addr, server) = fake_haproxy(1, 2);
+
+    let socket = Socket::new(addr.clone())?;
+    assert_eq!(socket.query("show info")?, "show info ok\nsecond line\n");
+    // A different Socket for the same address shares the connection
+    assert_eq!(Socket::new(addr)?.query("show stat")?, "show stat ok\nsecond line\n");
+
+    assert_eq!(server.join().unwrap(), vec!["prompt", "show info", "show stat"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_query_reconnects() -> Result<()> {
+    let (addr, server) = fake_haproxy(2, 1);
+
+    let socket = Socket::new(addr)?;
+    assert_eq!(socket.query("show info")?, "show info ok\nsecond line\n");
+    // The fake closes the connection after one command, the next query has to reconnect
+    assert_eq!(socket.query("show stat")?, "show stat ok\nsecond line\n");
+
+    assert_eq!(server.join().unwrap(), vec!["prompt", "show info", "prompt", "show stat"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_skips_missing_worker() -> Result<()> {
+    let (addr, server) = fake_haproxy_answering(1, 3, |command| match command {
+      "show proc" => "#<PID> <type> <reloads> <uptime> <version>\n1162 master 1 0d00h02m07s 2.8.3\n# workers\n\
+        1271 worker 0 0d00h00m10s 2.8.3\n# old workers\n1233 worker 1 0d00h02m07s 2.8.3\n"
+        .to_string(),
+      "@!1271 show stat" => include_str!("../../tests/fixtures/show_stat.csv").to_string(),
+      _ => "Can't find the target CLI!\n".to_string(),
+    });
+
+    let mut socket = Socket::new(addr)?;
+    let stats = socket.fetch_stats()?;
+    assert_eq!(stats.len(), 5);
+
+    let master = socket.master().unwrap();
+    assert_eq!(master.skipped, vec![1233]);
+    assert_eq!(master.processes.len(), 3);
+
+    assert_eq!(server.join().unwrap(), vec!["prompt", "show proc", "@!1271 show stat", "@!1233 show stat"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_keeps_counters_of_departed_worker() -> Result<()> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static SHOW_PROCS: AtomicUsize = AtomicUsize::new(0);
+
+    let (addr, server) = fake_haproxy_answering(1, 5, |command| match command {
+      // The old worker is gone by the second refresh
+      "show proc" if SHOW_PROCS.fetch_add(1, Ordering::SeqCst) == 0 => {
+        "#<PID> <type> <reloads> <uptime> <version>\n1162 master 1 0d00h02m07s 2.8.3\n# workers\n\
+        1271 worker 0 0d00h00m10s 2.8.3\n# old workers\n1233 worker 1 0d00h02m07s 2.8.3\n"
+          .to_string()
+      },
+      "show proc" => "#<PID> <type> <reloads> <uptime> <version>\n1162 master 1 0d00h02m07s 2.8.3\n# workers\n\
+        1271 worker 0 0d00h00m10s 2.8.3\n# old workers\n"
+        .to_string(),
+      _ => include_str!("../../tests/fixtures/show_stat.csv").to_string(),
+    });
+
+    let mut socket = Socket::new(addr)?;
+    let web = |stats: Vec<HaproxyStat>| stats.into_iter().find(|stat| stat.pxname.as_deref() == Some("web")).unwrap();
+    let before = web(socket.fetch_stats()?);
+    let after = web(socket.fetch_stats()?);
+    assert_eq!(before.req_tot, Some(3040.0));
+    assert_eq!(after.req_tot, Some(3040.0));
+    assert_eq!(after.stot, before.stot);
+    assert_eq!(after.scur, Some(3));
+
+    let received = server.join().unwrap();
+    assert_eq!(received[4..], ["show proc", "@!1271 show stat"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_on_each_worker() -> Result<()> {
+    let (addr, server) = fake_haproxy_answering(1, 5, |command| match command {
+      "show proc" => "#<PID> <type> <reloads> <uptime> <version>\n1162 master 1 0d00h02m07s 2.8.3\n# workers\n\
+        1271 worker 0 0d00h00m10s 2.8.3\n1272 worker 0 0d00h00m10s 2.8.3\n# old workers\n"
+        .to_string(),
+      "@!1271 show stat" | "@!1272 show stat" => include_str!("../../tests/fixtures/show_stat.csv").to_string(),
+      "@!1272 set server app/app1 state drain" => "No such server.\n".to_string(),
+      _ => "\n".to_string(),
+    });
+
+    let mut socket = Socket::new(addr)?;
+    socket.fetch_stats()?;
+    let responses = socket.run("set server app/app1 state drain")?;
+    assert_eq!(responses, vec![
+      WorkerResponse { pid: Some(1271), response: Ok(String::new()) },
+      WorkerResponse { pid: Some(1272), response: Err("No such server.".to_string()) },
+    ]);
+
+    let received = server.join().unwrap();
+    assert_eq!(received[4..], ["@!1271 set server app/app1 state drain", "@!1272 set server app/app1 state drain"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_run_without_workers() {
+    let mut socket = Socket::new("/nonexistent.sock".to_string()).unwrap();
+    socket.master = Some(true);
+    let error = socket.run("set server app/app1 state drain").unwrap_err();
+    assert!(error.to_string().starts_with("No current workers"));
   }
 }
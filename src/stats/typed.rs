@@ -0,0 +1,349 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::Display;
+use thiserror::Error;
+
+use super::data::HaproxyStat;
+
+#[derive(Error, Debug)]
+pub enum TypedError {
+  #[error("Invalid typed stat line: {0}")]
+  InvalidLine(String),
+  #[error("Unexpected show stat json layout")]
+  InvalidJson,
+}
+
+/// Which `show stat` output format to request from a stats socket.
+#[derive(Default, Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatFormat {
+  #[default]
+  Csv,
+  Json,
+  Typed,
+}
+
+/// Where a field's value comes from.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldOrigin {
+  Metric,
+  Status,
+  Key,
+  Config,
+  Product,
+  #[serde(other)]
+  Unknown,
+}
+
+/// What a field's value represents, which decides how it evolves between two snapshots.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldNature {
+  Age,
+  Avg,
+  Counter,
+  Duration,
+  Gauge,
+  Limit,
+  Max,
+  Min,
+  Name,
+  Output,
+  Rate,
+  Time,
+  #[serde(other)]
+  Unknown,
+}
+
+/// How far a field's value is shared.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldScope {
+  Cluster,
+  Process,
+  Service,
+  System,
+  #[serde(other)]
+  Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldMeta {
+  pub origin: FieldOrigin,
+  pub nature: FieldNature,
+  pub scope: FieldScope,
+  /// Value type as reported by HAProxy, e.g. `u64` or `str`.
+  pub kind: String,
+  pub description: Option<String>,
+}
+
+impl FieldMeta {
+  /// Counters only ever grow, so their interesting value is the change per second.
+  pub fn needs_rate(&self) -> bool {
+    self.nature == FieldNature::Counter
+  }
+}
+
+/// Metadata for every field HAProxy reported, keyed by field name.
+pub type FieldMetadata = BTreeMap<String, FieldMeta>;
+
+/// Stats parsed from `show stat typed` or `show stat json`, along with what the fields mean.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TypedStats {
+  pub stats: Vec<HaproxyStat>,
+  pub fields: FieldMetadata,
+}
+
+/// Identifies the proxy, listener or server a field belongs to: object type, proxy id, object id and process.
+type ObjectKey = (String, i64, i64, i64);
+
+/// Collects fields per object, keeping objects in the order HAProxy lists them.
+#[derive(Default)]
+struct Objects {
+  order: Vec<ObjectKey>,
+  columns: BTreeMap<ObjectKey, Vec<(String, String)>>,
+  fields: FieldMetadata,
+}
+
+impl Objects {
+  fn push(&mut self, key: ObjectKey, name: String, value: String, meta: FieldMeta) {
+    let columns = self.columns.entry(key.clone()).or_insert_with(|| {
+      self.order.push(key);
+      Vec::new()
+    });
+    columns.push((name.clone(), value));
+    self.fields.entry(name).or_insert(meta);
+  }
+
+  fn into_stats(mut self) -> Result<TypedStats> {
+    let mut stats = Vec::new();
+    for key in &self.order {
+      let columns = self.columns.remove(key).unwrap_or_default();
+      // Listeners aren't part of the CSV output either
+      if key.0 == "L" || key.0 == "Listener" {
+        continue;
+      }
+      stats.push(HaproxyStat::from_columns(columns.iter().map(|(name, value)| (name.as_str(), value.as_str())))?);
+    }
+    Ok(TypedStats { stats, fields: self.fields })
+  }
+}
+
+fn origin_from_tag(tag: char) -> FieldOrigin {
+  match tag {
+    'M' => FieldOrigin::Metric,
+    'S' => FieldOrigin::Status,
+    'K' => FieldOrigin::Key,
+    'C' => FieldOrigin::Config,
+    'P' => FieldOrigin::Product,
+    _ => FieldOrigin::Unknown,
+  }
+}
+
+fn nature_from_tag(tag: char) -> FieldNature {
+  match tag {
+    'A' => FieldNature::Age,
+    'a' => FieldNature::Avg,
+    'C' => FieldNature::Counter,
+    'D' => FieldNature::Duration,
+    'G' => FieldNature::Gauge,
+    'L' => FieldNature::Limit,
+    'M' => FieldNature::Max,
+    'm' => FieldNature::Min,
+    'N' => FieldNature::Name,
+    'O' => FieldNature::Output,
+    'R' => FieldNature::Rate,
+    'T' => FieldNature::Time,
+    _ => FieldNature::Unknown,
+  }
+}
+
+fn scope_from_tag(tag: char) -> FieldScope {
+  match tag {
+    'C' => FieldScope::Cluster,
+    'P' => FieldScope::Process,
+    'S' => FieldScope::Service,
+    's' => FieldScope::System,
+    _ => FieldScope::Unknown,
+  }
+}
+
+/// Parses `show stat typed`, where every line holds one field:
+/// `<type>.<proxy id>.<id>.<position>.<name>.<process>:<origin><nature><scope>:<value type>:<value>`.
+pub fn parse_typed(resp: &str) -> Result<TypedStats> {
+  let mut objects = Objects::default();
+
+  for line in resp.lines().map(str::trim).filter(|line| !line.is_empty()) {
+    let invalid = || TypedError::InvalidLine(line.to_string());
+
+    // The value may contain colons itself, e.g. an address
+    let mut parts = line.splitn(4, ':');
+    let (key, tags, kind, value) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+      (Some(key), Some(tags), Some(kind), Some(value)) => (key, tags, kind, value),
+      _ => return Err(invalid().into()),
+    };
+
+    let key: Vec<&str> = key.split('.').collect();
+    let tags: Vec<char> = tags.chars().collect();
+    if key.len() != 6 || tags.len() != 3 {
+      return Err(invalid().into());
+    }
+    let object = (key[0].to_string(), key[1].parse()?, key[2].parse()?, key[5].parse()?);
+
+    let meta = FieldMeta {
+      origin: origin_from_tag(tags[0]),
+      nature: nature_from_tag(tags[1]),
+      scope: scope_from_tag(tags[2]),
+      kind: kind.to_string(),
+      description: None,
+    };
+    objects.push(object, key[4].to_string(), value.to_string(), meta);
+  }
+
+  objects.into_stats()
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonField {
+  obj_type: String,
+  proxy_id: i64,
+  id: i64,
+  #[serde(default)]
+  process_num: i64,
+  field: JsonFieldName,
+  tags: JsonTags,
+  value: JsonValue,
+}
+
+#[derive(Deserialize)]
+struct JsonFieldName {
+  name: String,
+  #[serde(default)]
+  desc: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JsonTags {
+  origin: FieldOrigin,
+  nature: FieldNature,
+  scope: FieldScope,
+}
+
+#[derive(Deserialize)]
+struct JsonValue {
+  #[serde(rename = "type")]
+  kind: String,
+  value: Value,
+}
+
+/// Parses `show stat json`: a list of objects, each a list of fields with their tags and typed value.
+pub fn parse_json(resp: &str) -> Result<TypedStats> {
+  let lines: Vec<Vec<JsonField>> = serde_json::from_str(resp.trim()).map_err(|_| TypedError::InvalidJson)?;
+  let mut objects = Objects::default();
+
+  for field in lines.into_iter().flatten() {
+    let value = match field.value.value {
+      Value::String(value) => value,
+      value => value.to_string(),
+    };
+    let meta = FieldMeta {
+      origin: field.tags.origin,
+      nature: field.tags.nature,
+      scope: field.tags.scope,
+      kind: field.value.kind,
+      description: field.field.desc,
+    };
+    objects.push((field.obj_type, field.proxy_id, field.id, field.process_num), field.field.name, value, meta);
+  }
+
+  objects.into_stats()
+}
+
+/// Pulls field descriptions out of `show stat desc typed`, which ends every line of `show stat typed` with the
+/// field's description in quotes, keyed by field name.
+pub fn parse_descriptions(resp: &str) -> BTreeMap<String, String> {
+  let mut descriptions = BTreeMap::new();
+  for line in resp.lines().map(str::trim) {
+    let name = line.split(':').next().and_then(|key| key.split('.').nth(4));
+    // Values can contain colons and aren't quoted, so the description starts at the last `:"`
+    let description = line.strip_suffix('"').and_then(|line| line.rsplit_once(":\"")).map(|(_, desc)| desc);
+    if let (Some(name), Some(description)) = (name, description) {
+      descriptions.entry(name.to_string()).or_insert_with(|| description.to_string());
+    }
+  }
+  descriptions
+}
+
+#[cfg(test)]
+mod tests {
+  use pretty_assertions::assert_eq;
+
+  use super::*;
+
+  fn assert_stats(typed: &TypedStats) {
+    assert_eq!(typed.stats.len(), 3);
+
+    let web = &typed.stats[0];
+    assert_eq!(web.pxname.as_deref(), Some("web"));
+    assert_eq!(web.svname.as_deref(), Some("FRONTEND"));
+    assert_eq!(web.resource_type, Some(0));
+    assert_eq!(web.scur, Some(3));
+    assert_eq!(web.req_tot, Some(1520.0));
+    assert_eq!(web.extra.get("h1sess").map(String::as_str), Some("1520"));
+
+    let app1 = &typed.stats[1];
+    assert_eq!(app1.svname.as_deref(), Some("app1"));
+    assert_eq!(app1.addr.as_deref(), Some("10.0.0.11:8080"));
+    assert_eq!(app1.status.as_deref(), Some("UP"));
+    // Fields HAProxy leaves out are empty
+    assert_eq!(app1.qlimit, None);
+
+    assert_eq!(typed.stats[2].svname.as_deref(), Some("BACKEND"));
+
+    assert!(typed.fields["stot"].needs_rate());
+    assert!(!typed.fields["scur"].needs_rate());
+    assert_eq!(typed.fields["scur"].nature, FieldNature::Gauge);
+    assert_eq!(typed.fields["slim"].nature, FieldNature::Limit);
+    assert_eq!(typed.fields["smax"].nature, FieldNature::Max);
+    assert_eq!(typed.fields["pxname"].origin, FieldOrigin::Key);
+    assert_eq!(typed.fields["scur"].kind, "u32");
+  }
+
+  #[test]
+  fn test_parse_typed() {
+    let typed = parse_typed(include_str!("../../tests/fixtures/show_stat_typed.txt")).unwrap();
+    assert_stats(&typed);
+    assert_eq!(typed.fields["stot"].scope, FieldScope::Process);
+  }
+
+  #[test]
+  fn test_parse_typed_invalid() {
+    assert!(parse_typed("Unknown command: 'show stat typed'").is_err());
+  }
+
+  #[test]
+  fn test_parse_json() {
+    let typed = parse_json(include_str!("../../tests/fixtures/show_stat.json")).unwrap();
+    assert_stats(&typed);
+    assert_eq!(typed.fields["pxname"].description.as_deref(), Some("Proxy name"));
+  }
+
+  #[test]
+  fn test_parse_descriptions() {
+    let descriptions = parse_descriptions(include_str!("../../tests/fixtures/show_stat_desc_typed.txt"));
+    assert_eq!(descriptions.get("pxname").map(String::as_str), Some("Proxy name"));
+    let slim = descriptions.get("slim").map(String::as_str);
+    assert_eq!(slim, Some("Frontend/listener/server's maxconn, backend's fullconn"));
+    assert_eq!(
+      descriptions.get("addr").map(String::as_str),
+      Some("Server's address:port, shown only if show-legends is set, or at levels oper/admin for the CLI")
+    );
+    assert_eq!(descriptions.len(), 9);
+
+    // Without desc there's nothing to find, even in values with colons
+    assert!(parse_descriptions("S.4.1.73.addr.1:CGS:str:10.0.0.11:8080").is_empty());
+    assert!(parse_descriptions("Unknown command: 'show stat desc typed'").is_empty());
+  }
+}
@@ -0,0 +1,45 @@
+#!/usr/bin/env bash
+# Captures tests/fixtures/show_stat-<version>.csv from the official HAProxy images, running capture.cfg with one
+# server that answers (app1) and one that refuses connections (app2), after sending some requests through `web`.
+#
+# Usage: tests/fixtures/capture.sh [version...]    (default: 1.8 2.0 2.4 2.8 3.0)
+#
+# The tests in src/stats/data.rs check the captured values, so update them along with the fixtures.
+set -euo pipefail
+
+cd "$(dirname "$0")"
+versions=("$@")
+if [ ${#versions[@]} -eq 0 ]; then
+  versions=(1.8 2.0 2.4 2.8 3.0)
+fi
+requests=${REQUESTS:-20}
+network=h8r-fixtures
+
+cleanup() {
+  docker rm -f h8r-haproxy h8r-app1 h8r-app2 >/dev/null 2>&1 || true
+  docker network rm "$network" >/dev/null 2>&1 || true
+}
+trap cleanup EXIT
+cleanup
+
+docker network create --subnet 10.0.0.0/24 "$network" >/dev/null
+docker run -d --name h8r-app1 --network "$network" --ip 10.0.0.11 busybox httpd -f -p 8080 >/dev/null
+docker run -d --name h8r-app2 --network "$network" --ip 10.0.0.12 busybox sleep 3600 >/dev/null
+
+# Runs `sh -c "$1"` in a throwaway container on the fixture network
+on_network() {
+  docker run --rm --network "$network" busybox sh -c "$1"
+}
+
+for version in "${versions[@]}"; do
+  echo "Capturing HAProxy $version"
+  docker run -d --name h8r-haproxy --network "$network" --ip 10.0.0.10 \
+    -v "$PWD/capture.cfg:/usr/local/etc/haproxy/haproxy.cfg:ro" "haproxy:$version" >/dev/null
+
+  # Give the checks time to mark app2 down
+  sleep 5
+  on_network "for i in \$(seq $requests); do wget -q -O /dev/null http://10.0.0.10:8080/ || true; done"
+  on_network 'echo "show stat" | nc 10.0.0.10 9999' >"show_stat-$version.csv"
+
+  docker rm -f h8r-haproxy >/dev/null
+done